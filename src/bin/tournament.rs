@@ -0,0 +1,479 @@
+//! 无界面的引擎比赛：从 engines.json 读取引擎，进行循环赛或挑战赛，
+//! 支持同时下多局、SPRT 提前结束和开局库，并把每一局的棋谱和比赛结果写入输出目录。
+//!
+//! ```text
+//! tournament --board renju --games 100 --concurrency 4 \
+//!     --gauntlet new_net --sprt 0,10 --openings openings.txt --out results
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use rboard::{
+    chessboard::{MAX_BOARD_SIZE, MIN_BOARD_SIZE, get_all_board_names},
+    engine::{
+        engine_args::EngineArgs,
+        engine_paths::EnginePaths,
+        match_runner::{
+            GameRecord, MatchEngine, MatchScore, Sprt, opening_for, parse_openings, play_game,
+        },
+    },
+};
+
+const USAGE: &str = "用法: tournament [选项]
+  --engines FILE        引擎列表，默认为当前目录下的 engines.json
+  --engine NAME         参赛的引擎，可以重复，默认为全部引擎
+  --gauntlet NAME       挑战赛：NAME 与其他引擎各下一组，默认为循环赛
+  --board NAME          棋盘标识，默认为 gomoku
+  --size WxH            棋盘大小，默认为该棋盘的默认大小
+  --games N             每组对局数，默认为 10
+  --concurrency N       同时进行的对局数，默认为 1
+  --openings FILE       开局库，每行一个开局，例如 H8 I9 J7
+  --sprt E0,E1[,A,B]    SPRT：H0 为高出 E0 Elo，H1 为高出 E1 Elo，两类错误概率默认 0.05
+  --out DIR             棋谱和比赛结果的输出目录
+  --help                显示帮助";
+
+struct Options {
+    engines: PathBuf,
+    names: Vec<String>,
+    gauntlet: Option<String>,
+    board: String,
+    size: Option<(u32, u32)>,
+    games: u32,
+    concurrency: usize,
+    openings: Vec<Vec<String>>,
+    sprt: Option<Sprt>,
+    out: Option<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        engines: PathBuf::from("engines.json"),
+        names: vec![],
+        gauntlet: None,
+        board: "gomoku".to_string(),
+        size: None,
+        games: 10,
+        concurrency: 1,
+        openings: vec![],
+        sprt: None,
+        out: None,
+    };
+    while let Some(arg) = args.next() {
+        if arg == "--help" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or(format!("{} 缺少参数\n{}", arg, USAGE))?;
+        let invalid = || format!("无效的参数: {} {}", arg, value);
+        match arg.as_str() {
+            "--engines" => options.engines = PathBuf::from(&value),
+            "--engine" => options.names.push(value),
+            "--gauntlet" => options.gauntlet = Some(value),
+            "--board" => {
+                if !get_all_board_names().iter().any(|(_, id)| *id == value) {
+                    return Err(format!("未知的棋盘: {}", value));
+                }
+                options.board = value;
+            }
+            "--size" => {
+                let (w, h) = value.split_once(['x', '*']).ok_or_else(invalid)?;
+                let w = w.trim().parse().map_err(|_| invalid())?;
+                let h = h.trim().parse().map_err(|_| invalid())?;
+                let valid = MIN_BOARD_SIZE..=MAX_BOARD_SIZE;
+                if !(valid.contains(&w) && valid.contains(&h)) {
+                    return Err(format!(
+                        "不支持的棋盘大小: {} * {}，边长为 {}～{}",
+                        w, h, MIN_BOARD_SIZE, MAX_BOARD_SIZE
+                    ));
+                }
+                options.size = Some((w, h));
+            }
+            "--games" => options.games = value.parse().map_err(|_| invalid())?,
+            "--concurrency" => {
+                options.concurrency = value.parse().map_err(|_| invalid())?;
+                options.concurrency = options.concurrency.max(1);
+            }
+            "--openings" => {
+                let s = std::fs::read_to_string(&value).map_err(|e| e.to_string())?;
+                options.openings = parse_openings(&s);
+            }
+            "--sprt" => {
+                let values = value
+                    .split(',')
+                    .map(|v| v.trim().parse::<f64>().map_err(|_| invalid()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (elo0, elo1, alpha, beta) = match values[..] {
+                    [elo0, elo1] => (elo0, elo1, 0.05, 0.05),
+                    [elo0, elo1, alpha, beta] => (elo0, elo1, alpha, beta),
+                    _ => return Err(invalid()),
+                };
+                options.sprt = Some(Sprt {
+                    elo0,
+                    elo1,
+                    alpha,
+                    beta,
+                });
+            }
+            "--out" => options.out = Some(PathBuf::from(&value)),
+            _ => return Err(format!("未知的选项: {}\n{}", arg, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+/// 一组对局：两个引擎交换先后手下 `games` 局。
+struct Pairing {
+    first: usize,
+    second: usize,
+    score: MatchScore,
+    //SPRT 的结论，`Some(true)` 为接受 H1
+    verdict: Option<bool>,
+    //出错的对局数，不计入成绩
+    errors: u32,
+}
+
+struct Tournament {
+    options: Options,
+    engines: Vec<EngineArgs>,
+    pairings: Vec<Mutex<Pairing>>,
+    //SPRT 得出结论或者引擎无法启动时停止这一组，正在进行的对局也会中止
+    stops: Vec<AtomicBool>,
+    //(第几组, 组内第几局)
+    jobs: Mutex<VecDeque<(usize, u32)>>,
+    total: usize,
+    finished: Mutex<usize>,
+}
+
+impl Tournament {
+    fn new(options: Options) -> Result<Self, String> {
+        let all = EnginePaths::load(&options.engines)
+            .ok_or(format!("无法读取引擎列表: {}", options.engines.display()))?
+            .paths;
+        let engines = if options.names.is_empty() {
+            all
+        } else {
+            options
+                .names
+                .iter()
+                .map(|name| {
+                    all.iter()
+                        .find(|e| e.name == *name)
+                        .cloned()
+                        .ok_or(format!("找不到引擎: {}", name))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let pairs = match &options.gauntlet {
+            Some(name) => {
+                let g = engines
+                    .iter()
+                    .position(|e| e.name == *name)
+                    .ok_or(format!("找不到引擎: {}", name))?;
+                (0..engines.len())
+                    .filter(|&i| i != g)
+                    .map(|i| (g, i))
+                    .collect::<Vec<_>>()
+            }
+            None => (0..engines.len())
+                .flat_map(|i| (i + 1..engines.len()).map(move |j| (i, j)))
+                .collect(),
+        };
+        if pairs.is_empty() {
+            return Err("至少需要两个引擎".to_string());
+        }
+        //按局数交错排列，SPRT 提前结束的组不会拖住其他组
+        let jobs = (0..options.games)
+            .flat_map(|game| (0..pairs.len()).map(move |pair| (pair, game)))
+            .collect::<VecDeque<_>>();
+        let stops = pairs.iter().map(|_| AtomicBool::new(false)).collect();
+        let pairings = pairs
+            .into_iter()
+            .map(|(first, second)| {
+                Mutex::new(Pairing {
+                    first,
+                    second,
+                    score: MatchScore::default(),
+                    verdict: None,
+                    errors: 0,
+                })
+            })
+            .collect();
+        Ok(Tournament {
+            total: jobs.len(),
+            options,
+            engines,
+            pairings,
+            stops,
+            jobs: Mutex::new(jobs),
+            finished: Mutex::new(0),
+        })
+    }
+
+    //每个线程各自启动需要的引擎，下完全部对局后退出
+    fn worker(&self) {
+        let mut started: HashMap<usize, MatchEngine> = HashMap::new();
+        loop {
+            let Some((pair, game)) = self.jobs.lock().unwrap().pop_front() else {
+                break;
+            };
+            if self.stops[pair].load(Ordering::Relaxed) {
+                continue;
+            }
+            let (first, second) = {
+                let pairing = self.pairings[pair].lock().unwrap();
+                (pairing.first, pairing.second)
+            };
+            let result = self.play(&mut started, pair, game, first, second);
+            match result {
+                Ok(record) => self.report(pair, record),
+                Err(e) => {
+                    if !self.stops[pair].load(Ordering::Relaxed) {
+                        self.report_error(pair, game, e);
+                    }
+                    //引擎可能已经处于错误的状态，下一局重新启动
+                    started.remove(&first);
+                    started.remove(&second);
+                }
+            }
+        }
+    }
+
+    fn play(
+        &self,
+        started: &mut HashMap<usize, MatchEngine>,
+        pair: usize,
+        game: u32,
+        first: usize,
+        second: usize,
+    ) -> Result<GameRecord, String> {
+        for i in [first, second] {
+            if let Entry::Vacant(entry) = started.entry(i) {
+                match MatchEngine::start(&self.engines[i]) {
+                    Ok(engine) => {
+                        entry.insert(engine);
+                    }
+                    Err(e) => {
+                        //引擎无法启动，放弃这一组剩下的对局
+                        eprintln!("无法启动 {}: {}", self.engines[i].name, e);
+                        self.stops[pair].store(true, Ordering::Relaxed);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        let first_black = game.is_multiple_of(2);
+        let (black, white) = if first_black {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let mut black_engine = started.remove(&black).unwrap();
+        let mut white_engine = started.remove(&white).unwrap();
+        let result = play_game(
+            &self.options.board,
+            self.options.size,
+            opening_for(&self.options.openings, game),
+            &mut black_engine,
+            &mut white_engine,
+            &self.stops[pair],
+        );
+        started.insert(black, black_engine);
+        started.insert(white, white_engine);
+        let mut record = result?;
+        record.index = game;
+        record.first_black = first_black;
+        Ok(record)
+    }
+
+    fn report(&self, pair: usize, record: GameRecord) {
+        let mut pairing = self.pairings[pair].lock().unwrap();
+        pairing.score.add(&record);
+        let (first, second) = (
+            &self.engines[pairing.first].name,
+            &self.engines[pairing.second].name,
+        );
+        if let Some(out) = &self.options.out {
+            let file = out.join(format!(
+                "{}_vs_{}_{:03}.sgf",
+                first,
+                second,
+                record.index + 1
+            ));
+            if let Err(e) = std::fs::write(&file, &record.sgf) {
+                eprintln!("无法写入 {}: {}", file.display(), e);
+            }
+        }
+        if let Some(sprt) = &self.options.sprt
+            && pairing.verdict.is_none()
+        {
+            pairing.verdict = sprt.test(&pairing.score);
+            if pairing.verdict.is_some() {
+                self.stops[pair].store(true, Ordering::Relaxed);
+            }
+        }
+        let mut finished = self.finished.lock().unwrap();
+        *finished += 1;
+        println!(
+            "[{}/{}] {} vs {} 第 {} 局 {}（{} 执黑）  {}",
+            *finished,
+            self.total,
+            first,
+            second,
+            record.index + 1,
+            record.result,
+            record.black,
+            Self::progress(&self.engines[pairing.first].name, &pairing)
+        );
+    }
+
+    //对局出错（例如引擎崩溃）也算作下完的一局，进度不会停住
+    fn report_error(&self, pair: usize, game: u32, error: String) {
+        let mut pairing = self.pairings[pair].lock().unwrap();
+        pairing.errors += 1;
+        let mut finished = self.finished.lock().unwrap();
+        *finished += 1;
+        println!(
+            "[{}/{}] {} vs {} 第 {} 局出错: {}  {}",
+            *finished,
+            self.total,
+            self.engines[pairing.first].name,
+            self.engines[pairing.second].name,
+            game + 1,
+            error,
+            Self::progress(&self.engines[pairing.first].name, &pairing)
+        );
+    }
+
+    //一组当前的成绩，例如 `A 胜 3 负 2 和 1 出错 1`
+    fn progress(first: &str, pairing: &Pairing) -> String {
+        let score = pairing.score;
+        let mut s = format!(
+            "{} 胜 {} 负 {} 和 {}",
+            first, score.wins, score.losses, score.draws
+        );
+        if pairing.errors > 0 {
+            s.push_str(&format!(" 出错 {}", pairing.errors));
+        }
+        s
+    }
+
+    fn summary(&self) -> String {
+        let mut s = String::new();
+        s.push_str(&format!(
+            "棋盘 {}，每组 {} 局\n\n",
+            self.options.board, self.options.games
+        ));
+        //每个引擎的总成绩
+        let mut totals = vec![MatchScore::default(); self.engines.len()];
+        for pairing in &self.pairings {
+            let pairing = pairing.lock().unwrap();
+            let score = pairing.score;
+            let (elo, error) = score.elo();
+            s.push_str(&format!(
+                "{} vs {}: {} 局 胜 {} 负 {} 和 {}，得分率 {:.1}%，Elo {:+.1} ± {:.1}",
+                self.engines[pairing.first].name,
+                self.engines[pairing.second].name,
+                score.games(),
+                score.wins,
+                score.losses,
+                score.draws,
+                score.score() * 100.0,
+                elo,
+                error
+            ));
+            if pairing.errors > 0 {
+                s.push_str(&format!("，出错 {} 局", pairing.errors));
+            }
+            if let Some(sprt) = &self.options.sprt {
+                let (lower, upper) = sprt.bounds();
+                let verdict = match pairing.verdict {
+                    Some(true) => "接受 H1",
+                    Some(false) => "接受 H0",
+                    None => "未结束",
+                };
+                s.push_str(&format!(
+                    "，SPRT [{}, {}] LLR {:.2} ({:.2}, {:.2}) {}",
+                    sprt.elo0,
+                    sprt.elo1,
+                    score.llr(sprt.elo0, sprt.elo1),
+                    lower,
+                    upper,
+                    verdict
+                ));
+            }
+            s.push('\n');
+            let first = &mut totals[pairing.first];
+            first.wins += score.wins;
+            first.losses += score.losses;
+            first.draws += score.draws;
+            let second = &mut totals[pairing.second];
+            second.wins += score.losses;
+            second.losses += score.wins;
+            second.draws += score.draws;
+        }
+        let mut ranking = (0..self.engines.len())
+            .filter(|&i| totals[i].games() > 0)
+            .collect::<Vec<_>>();
+        ranking.sort_by(|&a, &b| totals[b].score().total_cmp(&totals[a].score()));
+        s.push_str("\n排名:\n");
+        for (rank, i) in ranking.into_iter().enumerate() {
+            let score = totals[i];
+            let (elo, error) = score.elo();
+            s.push_str(&format!(
+                "{:>3}. {:<20} {} 局 胜 {} 负 {} 和 {}，得分率 {:.1}%，Elo {:+.1} ± {:.1}\n",
+                rank + 1,
+                self.engines[i].name,
+                score.games(),
+                score.wins,
+                score.losses,
+                score.draws,
+                score.score() * 100.0,
+                elo,
+                error
+            ));
+        }
+        s
+    }
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(out) = &options.out
+        && let Err(e) = std::fs::create_dir_all(out)
+    {
+        eprintln!("无法创建输出目录 {}: {}", out.display(), e);
+        std::process::exit(1);
+    }
+    let tournament = match Tournament::new(options) {
+        Ok(tournament) => tournament,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    thread::scope(|scope| {
+        for _ in 0..tournament.options.concurrency {
+            scope.spawn(|| tournament.worker());
+        }
+    });
+    let summary = tournament.summary();
+    println!("\n{}", summary);
+    if let Some(out) = &tournament.options.out
+        && let Err(e) = std::fs::write(out.join("summary.txt"), &summary)
+    {
+        eprintln!("无法写入比赛结果: {}", e);
+    }
+}
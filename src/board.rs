@@ -1,15 +1,22 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use iced::{
-    Color, Point, Renderer, Theme,
+    Color, Point, Renderer, Size, Theme,
     mouse::{self, Cursor},
     widget::canvas::{self, Geometry, Stroke, Text},
 };
 
 use crate::{
-    chessboard::{chessboard_trait::ChessboardTrait, get_chessboard, get_piece},
+    chessboard::{
+        MAX_BOARD_SIZE, MIN_BOARD_SIZE,
+        chessboard_trait::{BoardShape, ChessboardTrait, GameResult, Mark, Player},
+        get_chessboard, get_column_name, hex, is_gomoku,
+    },
     engine::analyze::Analyzes,
+    game_tree::{GameInfo, GameTree},
     message::Message,
+    psq, renlib,
+    sgf::{self, Record},
 };
 
 pub struct Board {
@@ -17,6 +24,203 @@ pub struct Board {
     pub pieces: Vec<Vec<Option<(Color, Color)>>>,
     //引擎输出
     pub analyzes: Arc<Analyzes>,
+    //分析中每一手在棋盘上的位置，由 `ChessboardTrait::get_move` 解析
+    pub analysis_points: Vec<Option<(u32, u32)>>,
+    //获胜时连成一线的棋子
+    pub win_line: Vec<(u32, u32)>,
+    //额外标记，例如禁手点
+    pub marks: Vec<(u32, u32, Mark)>,
+    //一回合下多子时，当前玩家以及本回合的进度 (已下子数, 共需子数)
+    pub turn: Option<(Player, u32, u32)>,
+    //棋盘形状
+    pub shape: BoardShape,
+    //点击每个格子时实际落子的位置，见 `ChessboardTrait::resolve`
+    pub targets: Vec<Vec<Option<(u32, u32)>>>,
+    //摆棋模式：左键摆黑子，右键摆白子
+    pub edit: bool,
+}
+
+//棋盘在画布上的布局，负责格子坐标与画布坐标之间的换算
+struct Layout {
+    shape: BoardShape,
+    count: (u32, u32),
+    //相邻两个格子中心的距离
+    size: f32,
+    x_padding: f32,
+    y_padding: f32,
+}
+
+//六边形棋盘上下相邻两行中心的距离与 size 之比
+const HEX_ROW: f32 = 0.866;
+
+impl Layout {
+    fn new(shape: BoardShape, count: (u32, u32), bounds: Size) -> Self {
+        let (x, y) = count;
+        match shape {
+            BoardShape::Square => {
+                let x_size = (bounds.width - 5f32) / (x + 1) as f32;
+                let y_size = (bounds.height - 5f32) / (y + 1) as f32;
+                let size = x_size.min(y_size);
+                Layout {
+                    shape,
+                    count,
+                    size,
+                    x_padding: (bounds.width - size * x as f32) / 2f32 + size / 2f32,
+                    y_padding: (bounds.height - size * y as f32) / 2f32 + size / 2f32,
+                }
+            }
+            //每一行比上一行向右错开半格，整体是一个菱形
+            BoardShape::Hex => {
+                let width = x as f32 + (y - 1) as f32 / 2.0;
+                let height = (y - 1) as f32 * HEX_ROW + 1.0;
+                let x_size = (bounds.width - 5f32) / (width + 1.0);
+                let y_size = (bounds.height - 5f32) / (height + 1.0);
+                let size = x_size.min(y_size);
+                Layout {
+                    shape,
+                    count,
+                    size,
+                    x_padding: (bounds.width - size * width) / 2f32,
+                    y_padding: (bounds.height - size * height) / 2f32,
+                }
+            }
+        }
+    }
+
+    //格子 (i, j) 的中心
+    fn center(&self, i: u32, j: u32) -> Point {
+        match self.shape {
+            BoardShape::Square => Point::new(
+                self.x_padding + i as f32 * self.size + self.size / 2.0,
+                self.y_padding + j as f32 * self.size + self.size / 2.0,
+            ),
+            BoardShape::Hex => Point::new(
+                self.x_padding + (i as f32 + j as f32 / 2.0) * self.size + self.size / 2.0,
+                self.y_padding + j as f32 * HEX_ROW * self.size + self.size / 2.0,
+            ),
+        }
+    }
+
+    //画布上的点所在的格子
+    fn cell_at(&self, p: Point) -> Option<(i32, i32)> {
+        let (x, y) = self.count;
+        match self.shape {
+            BoardShape::Square => {
+                let x_count = ((p.x - self.x_padding) / self.size).floor() as i32;
+                let y_count = ((p.y - self.y_padding) / self.size).floor() as i32;
+                (x_count >= 0 && x_count < x as i32 && y_count >= 0 && y_count < y as i32)
+                    .then_some((x_count, y_count))
+            }
+            //六边形格子正好是离中心最近的区域
+            BoardShape::Hex => {
+                let j = ((p.y - self.y_padding - self.size / 2.0) / (HEX_ROW * self.size)).round();
+                let mut nearest = None;
+                let mut best = self.size / 3f32.sqrt();
+                for j in [j - 1.0, j, j + 1.0] {
+                    if j < 0.0 || j >= y as f32 {
+                        continue;
+                    }
+                    let i =
+                        ((p.x - self.x_padding - self.size / 2.0) / self.size - j / 2.0).round();
+                    for i in [i - 1.0, i, i + 1.0] {
+                        if i < 0.0 || i >= x as f32 {
+                            continue;
+                        }
+                        let d = p.distance(self.center(i as u32, j as u32));
+                        if d < best {
+                            best = d;
+                            nearest = Some((i as i32, j as i32));
+                        }
+                    }
+                }
+                nearest
+            }
+        }
+    }
+}
+
+//画六边形格子、坐标以及双方要连接的边：黑方连接上下两边，白方连接左右两边
+fn draw_hex_grid(frame: &mut canvas::Frame, layout: &Layout) {
+    let (x, y) = layout.count;
+    let size = layout.size;
+    let radius = size / 3f32.sqrt();
+    let corner = |center: Point, k: i32| {
+        let angle = std::f32::consts::PI / 3.0 * k as f32 + std::f32::consts::PI / 6.0;
+        Point::new(
+            center.x + radius * angle.cos(),
+            center.y + radius * angle.sin(),
+        )
+    };
+    for i in 0..x {
+        for j in 0..y {
+            let center = layout.center(i, j);
+            let hexagon = canvas::Path::new(|b| {
+                b.move_to(corner(center, 0));
+                for k in 1..6 {
+                    b.line_to(corner(center, k));
+                }
+                b.close();
+            });
+            frame.stroke(
+                &hexagon,
+                Stroke::default().with_width(1.0).with_color(Color::BLACK),
+            );
+        }
+    }
+
+    let edge = |from: Point, to: Point, color: Color| {
+        (
+            canvas::Path::line(from, to),
+            Stroke::default().with_width(4.0).with_color(color),
+        )
+    };
+    let offset = |p: Point, dx: f32, dy: f32| Point::new(p.x + dx * size, p.y + dy * size);
+    let white = Color::WHITE;
+    let black = Color::BLACK;
+    for (path, stroke) in [
+        edge(
+            offset(layout.center(0, 0), 0.0, -0.7),
+            offset(layout.center(x - 1, 0), 0.0, -0.7),
+            black,
+        ),
+        edge(
+            offset(layout.center(0, y - 1), 0.0, 0.7),
+            offset(layout.center(x - 1, y - 1), 0.0, 0.7),
+            black,
+        ),
+        edge(
+            offset(layout.center(0, 0), -0.65, 0.0),
+            offset(layout.center(0, y - 1), -0.65, 0.0),
+            white,
+        ),
+        edge(
+            offset(layout.center(x - 1, 0), 0.65, 0.0),
+            offset(layout.center(x - 1, y - 1), 0.65, 0.0),
+            white,
+        ),
+    ] {
+        frame.stroke(&path, stroke);
+    }
+
+    let label_size = iced::Pixels(size * 0.4);
+    for i in 0..x {
+        frame.fill_text(Text {
+            content: hex::column_name(i as i32),
+            position: offset(layout.center(i, 0), -0.15, -1.2),
+            color: Color::BLACK,
+            size: label_size,
+            ..Default::default()
+        });
+    }
+    for j in 0..y {
+        frame.fill_text(Text {
+            content: (j + 1).to_string(),
+            position: offset(layout.center(0, j), -1.3, -0.2),
+            color: Color::BLACK,
+            size: label_size,
+            ..Default::default()
+        });
+    }
 }
 
 impl canvas::Program<Message> for Board {
@@ -38,116 +242,116 @@ impl canvas::Program<Message> for Board {
         );
 
         let (x, y) = self.count;
-        let x_size = (bounds.width - 5f32) / (x + 1) as f32;
-        let y_size = (bounds.height - 5f32) / (y + 1) as f32;
-        let size = x_size.min(y_size);
-
-        let x_padding = (bounds.width - size * x as f32) / 2f32 + size / 2f32;
-        let y_padding = (bounds.height - size * y as f32) / 2f32 + size / 2f32;
-
-        for i in 0..=x {
-            let start = Point::new(x_padding + (i as f32) * size, y_padding);
-            let end = Point::new(x_padding + (i as f32) * size, (y as f32) * size + y_padding);
-            let line = canvas::Path::line(start, end);
-            frame.stroke(
-                &line,
-                Stroke::default()
-                    .with_width(1.0f32)
-                    .with_color(Color::BLACK),
-            );
-        }
+        let layout = Layout::new(self.shape, self.count, bounds.size());
+        let (size, x_padding, y_padding) = (layout.size, layout.x_padding, layout.y_padding);
 
-        for i in 0..=y {
-            let start = Point::new(x_padding, y_padding + (i as f32) * size);
-            let end = Point::new((x as f32) * size + x_padding, y_padding + (i as f32) * size);
-            let line = canvas::Path::line(start, end);
-            frame.stroke(
-                &line,
-                Stroke::default()
-                    .with_width(1.0f32)
-                    .with_color(Color::BLACK),
-            );
-        }
+        if self.shape == BoardShape::Hex {
+            draw_hex_grid(&mut frame, &layout);
+        } else {
+            for i in 0..=x {
+                let start = Point::new(x_padding + (i as f32) * size, y_padding);
+                let end = Point::new(x_padding + (i as f32) * size, (y as f32) * size + y_padding);
+                let line = canvas::Path::line(start, end);
+                frame.stroke(
+                    &line,
+                    Stroke::default()
+                        .with_width(1.0f32)
+                        .with_color(Color::BLACK),
+                );
+            }
 
-        for i in 0..x {
-            let mut b_x = i;
-            if i >= 'I' as u32 - 'A' as u32 {
-                b_x = b_x + 1;
+            for i in 0..=y {
+                let start = Point::new(x_padding, y_padding + (i as f32) * size);
+                let end = Point::new((x as f32) * size + x_padding, y_padding + (i as f32) * size);
+                let line = canvas::Path::line(start, end);
+                frame.stroke(
+                    &line,
+                    Stroke::default()
+                        .with_width(1.0f32)
+                        .with_color(Color::BLACK),
+                );
             }
-            let label = (b'A' + b_x as u8) as char;
 
-            let label_size = match label {
-                'i' | 'I' | 'J' => 0.2,
-                'm' | 'w' | 'M' => 0.7,
-                _ => 0.5,
-            };
-            let label = label.to_string();
+            for i in 0..x {
+                let label = get_column_name(i as i32);
 
-            let position = Point {
-                x: x_padding + i as f32 * size + (size * (1f32 - label_size)) / 2f32,
-                y: y_padding - size,
-            };
-            frame.fill_text(Text {
-                content: label,
-                position,
-                color: Color::BLACK,
-                size: iced::Pixels(size * 0.8),
-                ..Default::default()
-            });
-        }
+                let label_size = match label.as_str() {
+                    "I" | "J" => 0.2,
+                    "M" | "W" => 0.7,
+                    l if l.len() > 1 => 0.8,
+                    _ => 0.5,
+                };
 
-        for j in 0..y {
-            let mut label = (j + 1).to_string();
-            if j < 9 {
-                label = " ".to_owned() + &label;
+                let position = Point {
+                    x: x_padding + i as f32 * size + (size * (1f32 - label_size)) / 2f32,
+                    y: y_padding - size,
+                };
+                frame.fill_text(Text {
+                    content: label,
+                    position,
+                    color: Color::BLACK,
+                    size: iced::Pixels(size * 0.8),
+                    ..Default::default()
+                });
+            }
+
+            for j in 0..y {
+                let mut label = (j + 1).to_string();
+                if j < 9 {
+                    label = " ".to_owned() + &label;
+                }
+                let position = Point {
+                    x: x_padding - size,
+                    y: y_padding + (y - 1 - j) as f32 * size,
+                };
+                frame.fill_text(Text {
+                    content: label,
+                    position,
+                    color: Color::BLACK,
+                    size: iced::Pixels(size * 0.8),
+                    ..Default::default()
+                });
             }
-            let position = Point {
-                x: x_padding - size,
-                y: y_padding + (y - 1 - j) as f32 * size,
-            };
-            frame.fill_text(Text {
-                content: label,
-                position,
-                color: Color::BLACK,
-                size: iced::Pixels(size * 0.8),
-                ..Default::default()
-            });
         }
 
         //鼠标位置
         let p = cursor.position_in(bounds);
-        if let Some(p_cursor) = p {
-            let x_count = ((p_cursor.x - x_padding) / size).floor() as i32;
-            let y_count = ((p_cursor.y - y_padding) / size).floor() as i32;
-            if x_count >= 0 && x_count < x as i32 && y_count >= 0 && y_count < y as i32 {
-                // 定义颜色
-                let light_gray = Color::from_rgb(0.9, 0.9, 0.9); // 淡灰色背景
-                let purple = Color::from_rgb(0.5, 0.0, 0.5); // 紫色边框
-
-                let radius = size / 2.0;
-                // 设置圆的位置和大小
-                let center = iced::Point::new(
-                    x_padding + x_count as f32 * size + radius,
-                    y_padding + y_count as f32 * size + radius,
-                );
-                let circle = canvas::Path::circle(center, radius * 0.8);
+        if let Some((x_count, y_count)) = p.and_then(|p| layout.cell_at(p))
+            && let Some(Some((tx, ty))) = self
+                .targets
+                .get(x_count as usize)
+                .and_then(|column| column.get(y_count as usize))
+        {
+            // 定义颜色
+            let light_gray = Color::from_rgb(0.9, 0.9, 0.9); // 淡灰色背景
+            let purple = Color::from_rgb(0.5, 0.0, 0.5); // 紫色边框
 
-                frame.fill(&circle, light_gray);
+            let radius = size / 2.0;
+            // 设置圆的位置和大小
+            let center = layout.center(*tx, *ty);
+            let circle = canvas::Path::circle(center, radius * 0.8);
 
+            //棋子会落到别处时，画出从鼠标位置到落点的下落提示
+            if (*tx, *ty) != (x_count as u32, y_count as u32) {
                 frame.stroke(
-                    &circle,
+                    &canvas::Path::line(layout.center(x_count as u32, y_count as u32), center),
                     Stroke::default().with_color(purple).with_width(2.0),
                 );
             }
+
+            frame.fill(&circle, light_gray);
+
+            frame.stroke(
+                &circle,
+                Stroke::default().with_color(purple).with_width(2.0),
+            );
         }
 
         //画棋子
         for i in 0..self.pieces.len() {
             for j in 0..self.pieces[i].len() {
                 if let Some((c1, c2)) = self.pieces[i][j] {
-                    let x = x_padding + i as f32 * size + size / 2.0;
-                    let y = y_padding + j as f32 * size + size / 2.0;
-                    let center = iced::Point::new(x, y);
+                    let center = layout.center(i as u32, j as u32);
                     let circle = canvas::Path::circle(center, size / 2.0 * 0.9);
 
                     frame.fill(&circle, c1);
@@ -157,16 +361,70 @@ impl canvas::Program<Message> for Board {
             }
         }
 
+        //画标记
+        for (i, j, mark) in &self.marks {
+            let Point { x, y } = layout.center(*i, *j);
+            match mark {
+                Mark::Cross(color) => {
+                    let r = size * 0.2;
+                    let stroke = Stroke::default().with_color(*color).with_width(2.0);
+                    frame.stroke(
+                        &canvas::Path::line(Point::new(x - r, y - r), Point::new(x + r, y + r)),
+                        stroke,
+                    );
+                    frame.stroke(
+                        &canvas::Path::line(Point::new(x - r, y + r), Point::new(x + r, y - r)),
+                        stroke,
+                    );
+                }
+                Mark::Dot(color) => {
+                    frame.fill(&canvas::Path::circle(Point::new(x, y), size * 0.12), *color);
+                }
+            }
+        }
+
+        //一回合下多子时，在左上角提示正在下本回合的第几子
+        if let Some((player, placed, total)) = self.turn {
+            let name = if player == Player::Black {
+                "黑"
+            } else {
+                "白"
+            };
+            frame.fill_text(Text {
+                content: format!("{} {}/{}", name, placed + 1, total),
+                position: Point::new(5.0, 5.0),
+                color: Color::from_rgb8(241, 9, 9),
+                size: iced::Pixels(size * 0.8),
+                ..Default::default()
+            });
+        }
+
+        //画获胜连线，依次连接相邻的两个棋子
+        let red = Color::from_rgb8(241, 9, 9);
+        for p in &self.win_line {
+            frame.fill(
+                &canvas::Path::circle(layout.center(p.0, p.1), size * 0.12),
+                red,
+            );
+        }
+        for pair in self.win_line.windows(2) {
+            frame.stroke(
+                &canvas::Path::line(
+                    layout.center(pair[0].0, pair[0].1),
+                    layout.center(pair[1].0, pair[1].1),
+                ),
+                Stroke::default().with_color(red).with_width(3.0),
+            );
+        }
+
         //画预测棋子
         for i in 0..self.analyzes.datas.len() {
             let data = &self.analyzes.datas[i];
-            let (size_x, size_y) = self.count;
-            if let Some((x1, y1)) = get_piece(&data.move_, size_x, size_y, true) {
+            if let Some((x1, y1)) = self.analysis_points.get(i).copied().flatten() {
                 if i == 0 {
                     //best move
-                    let x = x_padding + x1 as f32 * size + size / 2.0;
-                    let y = y_padding + y1 as f32 * size + size / 2.0;
-                    let center = iced::Point::new(x, y);
+                    let center = layout.center(x1, y1);
+                    let Point { x, y } = center;
                     let circle = canvas::Path::circle(center, size / 2.0);
                     frame.fill(&circle, Color::from_rgba8(25, 118, 210, 0.7));
                     frame.stroke(
@@ -184,9 +442,8 @@ impl canvas::Program<Message> for Board {
                         ..Default::default()
                     });
                 } else if data.winrate > 0.7 {
-                    let x = x_padding + x1 as f32 * size + size / 2.0;
-                    let y = y_padding + y1 as f32 * size + size / 2.0;
-                    let center = iced::Point::new(x, y);
+                    let center = layout.center(x1, y1);
+                    let Point { x, y } = center;
                     let circle = canvas::Path::circle(center, size / 2.0);
                     frame.fill(&circle, Color::from_rgba8(187, 222, 251, 0.5));
                     frame.stroke(
@@ -204,9 +461,8 @@ impl canvas::Program<Message> for Board {
                         ..Default::default()
                     });
                 } else {
-                    let x = x_padding + x1 as f32 * size + size / 2.0;
-                    let y = y_padding + y1 as f32 * size + size / 2.0;
-                    let center = iced::Point::new(x, y);
+                    let center = layout.center(x1, y1);
+                    let Point { x, y } = center;
                     let circle = canvas::Path::circle(center, size / 2.0);
                     frame.fill(&circle, Color::from_rgba8(255, 205, 210, 0.3));
                     frame.stroke(
@@ -239,47 +495,444 @@ impl canvas::Program<Message> for Board {
         _bounds: iced::Rectangle,
         _cursor: mouse::Cursor,
     ) -> (canvas::event::Status, Option<Message>) {
-        match _event {
-            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
-                let (x, y) = self.count;
-                let x_size = (_bounds.width - 5f32) / (x + 1) as f32;
-                let y_size = (_bounds.height - 5f32) / (y + 1) as f32;
-                let size = x_size.min(y_size);
-
-                let x_padding = (_bounds.width - size * x as f32) / 2f32 + size / 2f32;
-                let y_padding = (_bounds.height - size * y as f32) / 2f32 + size / 2f32;
-                let p = _cursor.position_in(_bounds);
-                if let Some(p_cursor) = p {
-                    let x_count = ((p_cursor.x - x_padding) / size).floor() as i32;
-                    let y_count = ((p_cursor.y - y_padding) / size).floor() as i32;
-                    if x_count >= 0 && x_count < x as i32 && y_count >= 0 && y_count < y as i32 {
-                        return (
-                            canvas::event::Status::Ignored,
-                            Some(Message::GoBoard(x_count, y_count)),
-                        );
-                    }
-                }
-            }
-            _ => {}
-        }
-        (canvas::event::Status::Ignored, None)
+        let button = match _event {
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(button)) => button,
+            _ => return (canvas::event::Status::Ignored, None),
+        };
+        let layout = Layout::new(self.shape, self.count, _bounds.size());
+        let p = _cursor.position_in(_bounds);
+        let Some((x_count, y_count)) = p.and_then(|p| layout.cell_at(p)) else {
+            return (canvas::event::Status::Ignored, None);
+        };
+        let message = match (button, self.edit) {
+            (mouse::Button::Left, false) => Message::GoBoard(x_count, y_count),
+            (mouse::Button::Left, true) => Message::EditBoard(x_count, y_count, Player::Black),
+            (mouse::Button::Right, true) => Message::EditBoard(x_count, y_count, Player::White),
+            _ => return (canvas::event::Status::Ignored, None),
+        };
+        (canvas::event::Status::Ignored, Some(message))
     }
 }
 
 pub struct BoardState {
+    //棋盘的唯一标识
+    pub name: String,
     pub chessboard: Box<dyn ChessboardTrait>,
+    //棋谱树，棋盘总是对应当前节点的局面
+    pub tree: GameTree,
 }
 
 impl BoardState {
-    pub fn change_board(&mut self, name: String) {
-        self.chessboard = get_chessboard(name);
+    /// 切换棋盘类型，`size` 为 `None` 时使用默认尺寸。
+    pub fn change_board(&mut self, name: String, size: Option<(u32, u32)>) {
+        self.chessboard = get_chessboard(name.clone(), size);
+        self.name = name;
+        self.tree.clear();
+        self.tree.info.komi = self.chessboard.get_komi();
+    }
+
+    pub fn new_board(&mut self) {
+        self.chessboard.new_board();
+        self.tree.clear();
+        self.tree.info.komi = self.chessboard.get_komi();
+    }
+
+    /// 落子并记录到棋谱树中，`(x, y)` 为点击的格子，实际位置见 `ChessboardTrait::resolve`。
+    ///
+    /// 落子后对方无棋可走时自动替对方停一手，同样记录到棋谱树中。
+    ///
+    /// # 返回值
+    /// 成功返回发送给引擎的命令，每个元素一条，例如 `["play B D3", "play W pass"]`。
+    pub fn go(&mut self, x: i32, y: i32) -> Option<Vec<String>> {
+        if self.tree.current_node().resign {
+            return None;
+        }
+        let player = self.chessboard.get_player();
+        let (x, y) = self.chessboard.resolve(x, y)?;
+        let cmd = self.chessboard.go(x, y)?;
+        self.tree.play(player, x, y);
+        Some(self.auto_pass(vec![cmd]))
+    }
+
+    /// 终局后点目：切换 `(x, y)` 所在整块棋的死活，并把死子记录到棋谱树的当前节点。
+    /// 不能点目时返回 `false`。
+    pub fn toggle_dead(&mut self, x: i32, y: i32) -> bool {
+        if self.tree.current_node().resign {
+            return false;
+        }
+        match self.chessboard.toggle_dead(x, y) {
+            Some(dead) => {
+                self.tree.set_dead(dead);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 当前玩家停一手并记录到棋谱树中，返回值同 `go`。
+    pub fn pass(&mut self) -> Option<Vec<String>> {
+        if self.tree.current_node().resign {
+            return None;
+        }
+        let player = self.chessboard.get_player();
+        let cmd = self.chessboard.pass()?;
+        self.tree.pass(player);
+        Some(self.auto_pass(vec![cmd]))
+    }
+
+    //对方无棋可走时替对方停一手，命令追加到 `cmds` 之后
+    fn auto_pass(&mut self, mut cmds: Vec<String>) -> Vec<String> {
+        while self.chessboard.must_pass() {
+            let player = self.chessboard.get_player();
+            let Some(cmd) = self.chessboard.pass() else {
+                break;
+            };
+            self.tree.pass(player);
+            cmds.push(cmd);
+        }
+        cmds
+    }
+
+    /// 当前玩家认输，记录到棋谱树中。对局已经结束时返回 `false`。
+    pub fn resign(&mut self) -> bool {
+        if self.get_result().is_some() {
+            return false;
+        }
+        let player = self.chessboard.get_player();
+        self.tree.resign(player);
+        true
+    }
+
+    /// 保存到棋谱 `RE` 中的对局结果，由当前节点决定：认输为 `"B+R"`，分出胜负为 `"B+"`，和棋为 `"Draw"`。
+    /// 对局未结束时沿用载入的棋谱中的结果，认输之后悔棋不会留下过时的结果。
+    pub fn result_text(&self) -> String {
+        match self.get_result() {
+            Some(GameResult {
+                winner: Some(player),
+                ..
+            }) => {
+                let reason = if self.tree.current_node().resign {
+                    "R"
+                } else {
+                    ""
+                };
+                format!("{}+{}", player.gtp(), reason)
+            }
+            Some(_) => "Draw".to_string(),
+            None => self.tree.info.result.clone(),
+        }
+    }
+
+    /// 对局结果：当前节点为认输时对方获胜，否则由棋盘判断。
+    pub fn get_result(&self) -> Option<GameResult> {
+        let node = self.tree.current_node();
+        match node.player {
+            Some(player) if node.resign => Some(GameResult {
+                winner: Some(player.other()),
+                line: vec![],
+            }),
+            _ => self.chessboard.get_result(),
+        }
+    }
+
+    /// 进入摆棋：把当前局面作为初始局面，清除棋谱中的全部棋步，之后用 `edit` 增删棋子。
+    /// 界面在棋谱中有棋步时先请玩家确认，见 `has_moves`。
+    pub fn start_edit(&mut self) {
+        let mut setup = vec![];
+        for (x, column) in self.chessboard.get_owners().into_iter().enumerate() {
+            for (y, owner) in column.into_iter().enumerate() {
+                if let Some(player) = owner {
+                    setup.push((x as u32, y as u32, player));
+                }
+            }
+        }
+        let info = std::mem::take(&mut self.tree.info);
+        self.tree.clear();
+        self.tree.info = GameInfo {
+            result: String::new(),
+            setup,
+            to_play: Some(self.chessboard.get_player()),
+            ..info
+        };
+        self.replay();
+    }
+
+    /// 棋谱中是否有棋步，进入摆棋会清除它们。
+    pub fn has_moves(&self) -> bool {
+        !self.tree.node(self.tree.root()).children.is_empty()
+    }
+
+    /// 摆棋：在 `(x, y)` 摆上 `player` 的棋子，已经是同色的棋子时将其清除。
+    /// 只能在开局（棋谱树的根节点）摆棋，不能摆棋时返回 `false`。
+    pub fn edit(&mut self, x: i32, y: i32, player: Player) -> bool {
+        if self.tree.current() != self.tree.root() {
+            return false;
+        }
+        let existing = self
+            .tree
+            .info
+            .setup
+            .iter()
+            .find(|&&(sx, sy, _)| (sx as i32, sy as i32) == (x, y))
+            .map(|&(_, _, p)| p);
+        let (x, y, piece) = match existing {
+            Some(p) if p == player => (x, y, None),
+            Some(_) => (x, y, Some(player)),
+            None => match self.chessboard.resolve(x, y) {
+                Some((x, y)) => (x, y, Some(player)),
+                None => return false,
+            },
+        };
+        if !self.chessboard.set_piece(x, y, piece) {
+            return false;
+        }
+        let setup = &mut self.tree.info.setup;
+        setup.retain(|&(sx, sy, _)| (sx as i32, sy as i32) != (x, y));
+        if let Some(piece) = piece {
+            setup.push((x as u32, y as u32, piece));
+        }
+        true
+    }
+
+    /// 指定摆棋之后先走的一方。
+    pub fn set_to_play(&mut self, player: Player) {
+        self.tree.info.to_play = Some(player);
+        self.replay();
+    }
+
+    /// 让引擎摆出初始局面的命令。
+    ///
+    /// 引擎支持 `set_position` 时一次摆好全部棋子，例如 `"set_position B D4 W Q16"`；
+    /// 否则先 `clear_board`，再黑白交替用 `play` 摆子。没有摆棋时只有 `clear_board`。
+    pub fn setup_commands(&self, set_position: bool) -> Vec<String> {
+        let setup = &self.tree.info.setup;
+        if setup.is_empty() {
+            return vec!["clear_board".to_string()];
+        }
+        let stone = |&(x, y, player): &(u32, u32, Player)| {
+            format!(
+                "{} {}",
+                player.gtp(),
+                self.chessboard.get_coord(x as i32, y as i32)
+            )
+        };
+        if set_position {
+            let stones = setup.iter().map(stone).collect::<Vec<_>>();
+            return vec![format!("set_position {}", stones.join(" "))];
+        }
+        let (black, white): (Vec<_>, Vec<_>) =
+            setup.iter().partition(|&&(_, _, p)| p == Player::Black);
+        let mut cmds = vec!["clear_board".to_string()];
+        //一方的子摆完之后，另一方剩下的子依次摆出
+        for i in 0..black.len().max(white.len()) {
+            for s in [black.get(i), white.get(i)].into_iter().flatten() {
+                cmds.push(format!("play {}", stone(s)));
+            }
+        }
+        cmds
+    }
+
+    /// 悔一步棋，即回到父节点。
+    ///
+    /// 棋盘不一定能直接撤销一步（例如震棋会推动周围的棋子），
+    /// 所以这里重置棋盘并重放剩下的棋步。
+    ///
+    /// # 返回值
+    /// 成功返回 `Some(cmds)`，`cmds` 为重放时产生的全部落子命令，
+    /// 用于引擎不支持 `undo` 时重新同步局面；没有棋可悔时返回 `None`。
+    pub fn undo(&mut self) -> Option<Vec<String>> {
+        if !self.tree.back() {
+            return None;
+        }
+        Some(self.replay())
+    }
+
+    /// 沿最近走过的变化前进一步，返回落子命令，前进到认输时返回 `None`。
+    /// 棋盘拒绝这一步时（例如棋谱中的非法落子）停在原来的节点，同样返回 `None`。
+    pub fn redo(&mut self) -> Option<String> {
+        if !self.tree.forward() || self.tree.current_node().resign {
+            return None;
+        }
+        let cmd = match self.tree.current_node().mv {
+            Some((x, y)) => self.chessboard.go(x, y),
+            None => self.chessboard.pass(),
+        };
+        match cmd {
+            Some(_) => self.chessboard.set_dead(&self.tree.current_node().dead),
+            //退回去，保持棋谱树与棋盘的局面一致
+            None => {
+                self.tree.back();
+            }
+        }
+        cmd
+    }
+
+    /// 回到开局。
+    pub fn first(&mut self) -> Option<Vec<String>> {
+        self.tree.first().then(|| self.replay())
+    }
+
+    /// 前进到当前变化的最后一手。
+    pub fn last(&mut self) -> Option<Vec<String>> {
+        self.tree.last().then(|| self.replay())
+    }
+
+    /// 切换到兄弟变化。
+    pub fn switch_branch(&mut self, delta: i32) -> Option<Vec<String>> {
+        self.tree.switch_branch(delta).then(|| self.replay())
+    }
+
+    /// 跳转到棋谱树中的任意节点。
+    pub fn select(&mut self, index: usize) -> Option<Vec<String>> {
+        if index == self.tree.current() {
+            return None;
+        }
+        self.tree.select(index);
+        Some(self.replay())
+    }
+
+    /// 按扩展名载入棋谱文件（`.sgf`、RenLib 的 `.lib`、Piskvork 的 `.psq`），
+    /// 棋盘类型由棋谱决定，载入后停在开局。`.lib` 和 `.psq` 没有规则，
+    /// 当前为五子棋类的棋盘时保留当前的规则，否则为自由五子棋。
+    ///
+    /// # 返回值
+    /// 成功返回重放产生的落子命令，失败返回错误信息。
+    pub fn load_file(&mut self, path: &Path) -> Result<Vec<String>, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let gomoku = if is_gomoku(&self.name) {
+            self.name.as_str()
+        } else {
+            "gomoku"
+        };
+        let record = match extension(path).as_str() {
+            "lib" => renlib::read(&data, gomoku)?,
+            "psq" => psq::read(&String::from_utf8_lossy(&data), gomoku)?,
+            _ => sgf::read(&String::from_utf8_lossy(&data))?,
+        };
+        self.load(record)
+    }
+
+    /// 载入 SGF 文本，例如引擎对战的棋谱，返回值同 `load_file`。
+    pub fn load_sgf(&mut self, s: &str) -> Result<Vec<String>, String> {
+        self.load(sgf::read(s)?)
+    }
+
+    fn load(&mut self, record: Record) -> Result<Vec<String>, String> {
+        let valid = MIN_BOARD_SIZE..=MAX_BOARD_SIZE;
+        if let Some((w, h)) = record.size
+            && !(valid.contains(&w) && valid.contains(&h))
+        {
+            return Err(format!("不支持的棋盘大小: {} * {}", w, h));
+        }
+        let mut state = BoardState {
+            chessboard: get_chessboard(record.name.clone(), record.size),
+            name: record.name,
+            tree: record.tree,
+        };
+        match state.tree.info.komi {
+            Some(komi) => state.chessboard.set_komi(komi),
+            None => state.tree.info.komi = state.chessboard.get_komi(),
+        }
+        //逐条变化检查，棋盘拒绝的落子直接报错，否则之后棋盘会与棋谱树错开。
+        //倒序检查，最后走过的是主变化
+        for (_, line) in state.tree.lines().into_iter().rev() {
+            if let Some(&leaf) = line.last() {
+                state.tree.select(leaf);
+                state.try_replay()?;
+            }
+        }
+        state.tree.select(state.tree.root());
+        let cmds = state.replay();
+        *self = state;
+        Ok(cmds)
+    }
+
+    /// 按扩展名保存棋谱。`.psq` 只保存当前变化，`.sgf` 保存整个棋谱树。
+    pub fn save_file(&self, path: &Path) -> Result<(), String> {
+        let size = self.chessboard.get_length();
+        let s = match extension(path).as_str() {
+            //psq 没有停一手
+            "psq" => psq::write(
+                size,
+                &self
+                    .tree
+                    .active_line()
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>(),
+            ),
+            _ => {
+                let mut tree = self.tree.clone();
+                tree.info.result = self.result_text();
+                sgf::write(&self.name, size, &tree)
+            }
+        };
+        std::fs::write(path, s).map_err(|e| e.to_string())
+    }
+
+    /// 重置棋盘并重放当前节点的全部棋步，返回重放产生的落子命令。
+    pub fn replay(&mut self) -> Vec<String> {
+        self.place_setup();
+        let cmds = self
+            .tree
+            .path()
+            .into_iter()
+            .filter_map(|mv| match mv {
+                Some((x, y)) => self.chessboard.go(x, y),
+                None => self.chessboard.pass(),
+            })
+            .collect();
+        self.chessboard.set_dead(&self.tree.current_node().dead);
+        cmds
+    }
+
+    //同 `replay`，但棋盘拒绝某一手时返回错误
+    fn try_replay(&mut self) -> Result<Vec<String>, String> {
+        self.place_setup();
+        let mut cmds = vec![];
+        for (i, mv) in self.tree.path().into_iter().enumerate() {
+            let cmd = match mv {
+                Some((x, y)) => self.chessboard.go(x, y),
+                None => self.chessboard.pass(),
+            };
+            let Some(cmd) = cmd else {
+                let mv = mv.map_or("pass".to_string(), |(x, y)| self.chessboard.get_coord(x, y));
+                return Err(format!("第 {} 手 {} 无法落子", i + 1, mv));
+            };
+            cmds.push(cmd);
+        }
+        self.chessboard.set_dead(&self.tree.current_node().dead);
+        Ok(cmds)
+    }
+
+    //重置棋盘并摆出初始局面
+    fn place_setup(&mut self) {
+        self.chessboard.new_board();
+        let mut setup = self.tree.info.setup.clone();
+        //从下往上摆，重力棋的棋子不会悬空
+        setup.sort_by_key(|&(_, y, _)| std::cmp::Reverse(y));
+        for (x, y, piece) in setup {
+            self.chessboard.set_piece(x as i32, y as i32, Some(piece));
+        }
+        if let Some(player) = self.tree.info.to_play {
+            self.chessboard.set_player(player);
+        }
     }
 }
 
 impl Default for BoardState {
     fn default() -> Self {
         BoardState {
-            chessboard: get_chessboard("".to_string()),
+            name: "gomoku".to_string(),
+            chessboard: get_chessboard("gomoku".to_string(), None),
+            tree: GameTree::new(),
         }
     }
 }
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
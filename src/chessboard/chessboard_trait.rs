@@ -1,5 +1,7 @@
 use iced::Color;
 
+use crate::chessboard::Move;
+
 /// 棋盘操作的通用接口，定义了获取棋盘信息和下棋行为的方法。
 /// api不稳定，也许会改动
 pub trait ChessboardTrait {
@@ -18,6 +20,24 @@ pub trait ChessboardTrait {
     /// - `None`：落子失败（如非法位置等）。
     fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>>;
 
+    /// 获取每个格子上棋子所属的玩家，`None` 为空，坐标与 `get_pieces` 相同。
+    ///
+    /// 摆棋时用它把当前局面作为初始局面，与棋子画成什么颜色无关。
+    /// 默认实现返回空棋盘，支持摆棋（实现了 `set_piece`）的棋盘需要实现。
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        let (x, y) = self.get_length();
+        vec![vec![None; y as usize]; x as usize]
+    }
+
+    /// 把点击的格子换算成实际落子的位置，例如重力棋中棋子会落到该列最下方的空位。
+    ///
+    /// # 返回值
+    /// - `Some((x, y))`：实际落子的位置，之后以该位置调用 `go`。默认实现原样返回。
+    /// - `None`：该点击不能落子，例如该列已满。
+    fn resolve(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        Some((x, y))
+    }
+
     /// 尝试在指定坐标 `(x, y)` 下棋。
     ///
     /// # 参数
@@ -29,18 +49,198 @@ pub trait ChessboardTrait {
     /// - `None`：落子失败（如非法位置等）。
     fn go(&mut self, x: i32, y: i32) -> Option<String>;
 
+    /// 当前玩家停一手，例如围棋。
+    ///
+    /// # 返回值
+    /// - `Some(String)`：停一手成功，返回发送给引擎的命令，例如 `"play B pass"`。
+    /// - `None`：该棋盘不允许停一手，默认实现。
+    fn pass(&mut self) -> Option<String> {
+        None
+    }
+
+    /// 当前玩家是否无棋可走、必须停一手，例如黑白棋。
+    /// 返回 `true` 时界面会在落子后自动替对方停一手。
+    fn must_pass(&self) -> bool {
+        false
+    }
+
+    /// 终局后点目：切换 `(x, y)` 所在整块棋的死活，例如围棋双方连续停一手之后。
+    ///
+    /// # 返回值
+    /// - `Some(dead)`：切换成功，返回切换之后的全部死子，界面把它记录到棋谱树的当前节点。
+    /// - `None`：对局未结束、该位置没有棋子，或该棋盘不点目，默认实现。
+    fn toggle_dead(&mut self, _x: i32, _y: i32) -> Option<Vec<(u32, u32)>> {
+        None
+    }
+
+    /// 设置终局时的死子，例如重放棋谱之后恢复记录在节点上的死子。对局未结束时忽略。
+    fn set_dead(&mut self, _dead: &[(u32, u32)]) {}
+
     /// 重置棋盘，开始新的一局。
     fn new_board(&mut self);
 
+    /// 摆棋：把 `(x, y)` 直接设为 `piece`，`None` 为清空。不按规则落子，也不提子、不判断胜负，
+    /// 用于摆出书上的局面。摆棋之后棋盘应当清除对局结果，并按新的局面继续对局。
+    ///
+    /// # 返回值
+    /// - `true`：摆棋成功。
+    /// - `false`：该位置不能摆棋（例如重力棋中悬空的位置），或该棋盘不支持摆棋，默认实现。
+    fn set_piece(&mut self, _x: i32, _y: i32, _piece: Option<Player>) -> bool {
+        false
+    }
+
+    /// 摆棋之后指定轮到哪一方落子。
+    fn set_player(&mut self, _player: Player) {}
+
     /// 获取当前执棋的玩家。
     ///
     /// # 返回值
     /// 返回当前轮到的玩家（例如黑方或白方）。
     fn get_player(&self) -> Player;
+
+    /// 一回合需要下多子的棋盘（例如六子棋）返回当前回合的进度。
+    ///
+    /// # 返回值
+    /// - `Some((placed, total))`：本回合已经下了 `placed` 子，共需下 `total` 子。
+    ///   `go` 每下一子都返回一条命令，下满 `total` 子后 `get_player` 才切换到对方。
+    ///   `placed` 大于 0 时本回合还没下完，界面不分析这个局面。
+    /// - `None`：每回合只下一子，默认实现。
+    fn get_turn(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// 把 `(x, y)` 写成发送给引擎的坐标。
+    ///
+    /// # 返回值
+    /// 默认为 GTP 的坐标，例如 `"H8"`，见 `chessboard::get_coord`。
+    fn get_coord(&self, x: i32, y: i32) -> String {
+        crate::chessboard::get_coord(x, y, self.get_length().1)
+    }
+
+    /// 解析引擎给出的一手棋，坐标格式与 `get_coord` 相同。
+    ///
+    /// # 返回值
+    /// 默认同 `chessboard::get_move`，无法识别或超出棋盘时返回 `None`。
+    fn get_move(&self, s: &str) -> Option<Move> {
+        let (x, y) = self.get_length();
+        crate::chessboard::get_move(s, x, y)
+    }
+
+    /// 获取棋盘形状，决定界面如何绘制格子以及如何把点击换算成坐标。
+    ///
+    /// # 返回值
+    /// 默认为 `BoardShape::Square`。
+    fn get_shape(&self) -> BoardShape {
+        BoardShape::Square
+    }
+
+    /// 获取对局结果。
+    ///
+    /// # 返回值
+    /// - `Some(GameResult)`：对局已结束，此时 `go` 应当拒绝继续落子。
+    /// - `None`：对局未结束，或该棋盘不判断胜负。
+    fn get_result(&self) -> Option<GameResult> {
+        None
+    }
+
+    /// 获取棋盘上需要额外标记的交叉点，例如连珠中黑方的禁手点。
+    ///
+    /// # 返回值
+    /// 返回 `(x, y, mark)` 列表，默认为空。
+    fn get_marks(&self) -> Vec<(u32, u32, Mark)> {
+        vec![]
+    }
+
+    /// 获取发送给引擎的规则名，引擎启动后通过 `kata-set-rules` 发送。
+    ///
+    /// # 返回值
+    /// 返回 `None` 表示不设置规则，使用引擎的默认规则。
+    fn get_rules(&self) -> Option<String> {
+        None
+    }
+
+    /// 获取贴目，引擎启动后通过 `komi` 发送，并写入棋谱的 `KM`。
+    ///
+    /// # 返回值
+    /// 返回 `None` 表示该棋盘没有贴目。
+    fn get_komi(&self) -> Option<f64> {
+        None
+    }
+
+    /// 设置贴目，例如从棋谱的 `KM` 读入。没有贴目的棋盘忽略即可。
+    fn set_komi(&mut self, _komi: f64) {}
+
+    /// 获取双方的提子数（黑方提走的白子，白方提走的黑子），显示在胜率条两侧。
+    ///
+    /// # 返回值
+    /// 返回 `None` 表示该棋盘没有提子，默认实现。
+    fn get_captures(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// 获取显示在胜率条下方的附加信息，例如提子数或数子结果。
+    fn get_info(&self) -> Option<String> {
+        None
+    }
+}
+
+/// 棋盘形状。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoardShape {
+    /// 方格棋盘
+    Square,
+    /// 六边形格子组成的菱形棋盘，第 `y` 行比上一行向右错开半格，
+    /// 格子 `(x, y)` 与 `(x ± 1, y)`、`(x, y ± 1)`、`(x + 1, y - 1)`、`(x - 1, y + 1)` 相邻
+    Hex,
+}
+
+/// 交叉点上的标记样式。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mark {
+    /// 叉号，例如禁手点
+    Cross(Color),
+    /// 小圆点，例如可以落子的位置
+    Dot(Color),
+}
+
+/// 对局结果。
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameResult {
+    /// 胜者，`None` 表示和棋
+    pub winner: Option<Player>,
+    /// 获胜时连成一线的棋子坐标 `(x, y)`，按顺序排列，界面会依次连接相邻的两子，可以为空
+    pub line: Vec<(u32, u32)>,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Player {
     Black,
     White,
 }
+
+impl Player {
+    /// 对方。
+    pub fn other(self) -> Player {
+        match self {
+            Player::Black => Player::White,
+            Player::White => Player::Black,
+        }
+    }
+
+    /// GTP 命令中的颜色，`"B"` 或 `"W"`。
+    pub fn gtp(self) -> &'static str {
+        match self {
+            Player::Black => "B",
+            Player::White => "W",
+        }
+    }
+
+    /// 解析 GTP 命令中的颜色，`B`/`W` 或 `black`/`white`，不区分大小写。
+    pub fn from_gtp(s: &str) -> Option<Player> {
+        match s.to_ascii_lowercase().as_str() {
+            "b" | "black" => Some(Player::Black),
+            "w" | "white" => Some(Player::White),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! 六子棋：黑方第一回合下一子，之后双方每回合下两子，六连及以上获胜。
+
+use iced::Color;
+
+use crate::chessboard::{
+    chessboard_trait::{ChessboardTrait, GameResult, Mark, Player},
+    get_coord,
+    gomoku::{DIRECTIONS, board_owners, get_cell, line_through, piece_value},
+};
+
+pub struct Connect6 {
+    // -1: empty 0: black 1: white
+    board: Vec<Vec<i32>>,
+    size_x: u32,
+    size_y: u32,
+    current_player: i32,
+    result: Option<GameResult>,
+    //第几回合，从 0 开始
+    turn: u32,
+    //本回合已经下的子
+    placed: Vec<(u32, u32)>,
+}
+
+impl Connect6 {
+    pub fn new(width: u32, height: u32) -> Self {
+        Connect6 {
+            board: vec![vec![-1; height as usize]; width as usize],
+            size_x: width,
+            size_y: height,
+            current_player: 0,
+            result: None,
+            turn: 0,
+            placed: vec![],
+        }
+    }
+
+    //本回合共需下几子
+    fn stones_per_turn(&self) -> u32 {
+        if self.turn == 0 { 1 } else { 2 }
+    }
+}
+
+impl Default for Connect6 {
+    fn default() -> Self {
+        Self::new(19, 19)
+    }
+}
+
+impl ChessboardTrait for Connect6 {
+    fn get_length(&self) -> (u32, u32) {
+        (self.size_x, self.size_y)
+    }
+    fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
+        let black = (Color::BLACK, Color::WHITE);
+        let white = (Color::WHITE, Color::BLACK);
+        self.board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| match cell {
+                        0 => Some(black),
+                        1 => Some(white),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// 每下一子返回一条 `play` 命令，同一回合的两子颜色相同，
+    /// 例如 `"play B J10"` 之后是 `"play B K10"`，引擎按顺序收到两手同色的棋。
+    /// GTP 的 `play` 只能表示一个点，所以不合并成一条命令；
+    /// 回合中途的局面不完整，界面在 `get_turn` 回到第一子之前不让引擎分析。
+    fn go(&mut self, x: i32, y: i32) -> Option<String> {
+        if x < 0 || x >= self.size_x as i32 || y < 0 || y >= self.size_y as i32 {
+            return None;
+        }
+        if self.result.is_some() || self.board[x as usize][y as usize] != -1 {
+            return None;
+        }
+        self.board[x as usize][y as usize] = self.current_player;
+        let player = self.get_player();
+        let p = if player == Player::Black { "B" } else { "W" };
+        if let Some(line) = DIRECTIONS
+            .iter()
+            .map(|&(dx, dy)| line_through(&self.board, x, y, dx, dy))
+            .find(|line| line.len() >= 6)
+        {
+            self.result = Some(GameResult {
+                winner: Some(player),
+                line,
+            });
+        } else if self.board.iter().flatten().all(|&cell| cell != -1) {
+            self.result = Some(GameResult {
+                winner: None,
+                line: vec![],
+            });
+        }
+        self.placed.push((x as u32, y as u32));
+        if self.placed.len() as u32 >= self.stones_per_turn() {
+            self.placed.clear();
+            self.turn += 1;
+            self.current_player = 1 - self.current_player;
+        }
+        Some(format!("play {} {}", p, get_coord(x, y, self.size_y)))
+    }
+    fn new_board(&mut self) {
+        self.board = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
+        self.current_player = 0;
+        self.result = None;
+        self.turn = 0;
+        self.placed.clear();
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        if get_cell(&self.board, x, y).is_none() {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        self.result = None;
+        //棋盘上已经有子时每回合下两子
+        self.turn = if self.board.iter().flatten().all(|&cell| cell == -1) {
+            0
+        } else {
+            1
+        };
+        self.placed.clear();
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
+        self.placed.clear();
+    }
+    fn get_player(&self) -> Player {
+        if self.current_player == 1 {
+            Player::White
+        } else {
+            Player::Black
+        }
+    }
+    fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
+    fn get_marks(&self) -> Vec<(u32, u32, Mark)> {
+        self.placed
+            .iter()
+            .map(|&(x, y)| (x, y, Mark::Dot(Color::from_rgb8(241, 9, 9))))
+            .collect()
+    }
+    fn get_turn(&self) -> Option<(u32, u32)> {
+        Some((self.placed.len() as u32, self.stones_per_turn()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_then_two_stones_per_turn() {
+        let mut board = Connect6::new(19, 19);
+        assert_eq!(board.get_turn(), Some((0, 1)));
+        assert_eq!(board.go(9, 9), Some("play B K10".to_string()));
+        //黑方第一回合只下一子
+        assert_eq!(board.get_player(), Player::White);
+        assert_eq!(board.get_turn(), Some((0, 2)));
+        assert_eq!(board.go(10, 9), Some("play W L10".to_string()));
+        //下完第一子仍然轮到白方
+        assert_eq!(board.get_player(), Player::White);
+        assert_eq!(board.get_turn(), Some((1, 2)));
+        assert_eq!(board.get_marks().len(), 1);
+        assert!(board.go(10, 9).is_none());
+        assert_eq!(board.go(11, 9), Some("play W M10".to_string()));
+        assert_eq!(board.get_player(), Player::Black);
+        assert_eq!(board.get_turn(), Some((0, 2)));
+        assert!(board.get_marks().is_empty());
+        board.go(0, 0);
+        board.go(0, 1);
+        assert_eq!(board.get_player(), Player::White);
+        board.new_board();
+        assert_eq!(board.get_turn(), Some((0, 1)));
+    }
+
+    #[test]
+    fn six_in_a_row_wins() {
+        let mut board = Connect6::new(19, 19);
+        //黑方下在第 0 列，白方的棋子互不相连
+        board.go(0, 0);
+        for i in 0..3 {
+            board.go(2 * i + 5, 15);
+            board.go(2 * i + 5, 17);
+            if i < 2 {
+                board.go(0, 2 * i + 1);
+                board.go(0, 2 * i + 2);
+            }
+        }
+        //五连不算获胜
+        assert!(board.get_result().is_none());
+        assert_eq!(board.get_player(), Player::Black);
+        board.go(0, 5);
+        let result = board.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::Black));
+        assert_eq!(result.line.len(), 6);
+        assert!(board.go(0, 6).is_none());
+    }
+}
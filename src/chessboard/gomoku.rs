@@ -1,25 +1,171 @@
 use iced::Color;
 
-use crate::chessboard::chessboard_trait::ChessboardTrait;
+use crate::chessboard::{
+    chessboard_trait::{ChessboardTrait, GameResult, Mark, Player},
+    get_coord,
+    renju::{ForbiddenRule, is_forbidden},
+};
+
+/// 五子棋的规则。
+#[derive(Clone, Copy, PartialEq)]
+pub enum GomokuRule {
+    /// 无禁手，五连及以上即胜
+    Freestyle,
+    /// 正好五连才算胜，长连不算
+    Standard,
+    /// 连珠，黑方有长连、四四、三三禁手
+    Renju,
+    /// 越南 Caro，五连及以上且两端没有同时被对方堵住才算胜
+    Caro,
+    /// 韩国 Omok，正好五连才算胜，黑方三三禁手
+    Omok,
+}
 
 pub struct Gomoku {
     // -1: empty 0: black 1: white
     board: Vec<Vec<i32>>,
+    size_x: u32,
+    size_y: u32,
     current_player: i32,
+    result: Option<GameResult>,
+    rule: GomokuRule,
+    //黑方当前的禁手点，轮到黑方时才计算
+    forbidden: Vec<(u32, u32)>,
 }
 
 impl Gomoku {
-    pub fn new() -> Self {
+    pub fn new(rule: GomokuRule, width: u32, height: u32) -> Self {
         Gomoku {
-            board: vec![vec![-1; 15]; 15],
+            board: vec![vec![-1; height as usize]; width as usize],
+            size_x: width,
+            size_y: height,
             current_player: 0,
+            result: None,
+            rule,
+            forbidden: vec![],
+        }
+    }
+
+    //检查 (x, y) 落子后按当前规则是否获胜，返回连成的棋子
+    fn find_five(&self, x: i32, y: i32) -> Option<Vec<(u32, u32)>> {
+        let color = self.board[x as usize][y as usize];
+        DIRECTIONS.iter().find_map(|&(dx, dy)| {
+            let line = line_through(&self.board, x, y, dx, dy);
+            let win = match self.rule {
+                GomokuRule::Freestyle => line.len() >= 5,
+                GomokuRule::Standard | GomokuRule::Omok => line.len() == 5,
+                GomokuRule::Renju => line.len() == 5 || (color == 1 && line.len() > 5),
+                GomokuRule::Caro => line.len() >= 5 && !self.blocked_both_ends(&line, dx, dy),
+            };
+            win.then_some(line)
+        })
+    }
+
+    //连线两端是否都紧挨着对方的棋子
+    fn blocked_both_ends(&self, line: &[(u32, u32)], dx: i32, dy: i32) -> bool {
+        let (fx, fy) = line[0];
+        let (lx, ly) = line[line.len() - 1];
+        let color = self.board[fx as usize][fy as usize];
+        //line 按坐标排序，起点一定在 (dx, dy) 的反方向上（dy = -1 时同样成立）
+        let before = get_cell(&self.board, fx as i32 - dx, fy as i32 - dy);
+        let after = get_cell(&self.board, lx as i32 + dx, ly as i32 + dy);
+        before == Some(1 - color) && after == Some(1 - color)
+    }
+
+    fn forbidden_rule(&self) -> Option<ForbiddenRule> {
+        match self.rule {
+            GomokuRule::Renju => Some(ForbiddenRule::Renju),
+            GomokuRule::Omok => Some(ForbiddenRule::DoubleThree),
+            _ => None,
+        }
+    }
+
+    fn update_forbidden(&mut self) {
+        self.forbidden.clear();
+        let Some(rule) = self.forbidden_rule() else {
+            return;
+        };
+        if self.current_player != 0 || self.result.is_some() {
+            return;
+        }
+        for x in 0..self.board.len() {
+            for y in 0..self.board[x].len() {
+                if is_forbidden(&mut self.board, x as i32, y as i32, rule) {
+                    self.forbidden.push((x as u32, y as u32));
+                }
+            }
+        }
+    }
+}
+
+impl Default for Gomoku {
+    fn default() -> Self {
+        Self::new(GomokuRule::Freestyle, 15, 15)
+    }
+}
+
+/// 横、竖、两条斜线四个方向。
+pub(crate) const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// 取得经过 `(x, y)`、沿 `(dx, dy)` 方向与它同色且连续的全部棋子（包括它自己），按坐标排序。
+pub(crate) fn line_through(
+    board: &[Vec<i32>],
+    x: i32,
+    y: i32,
+    dx: i32,
+    dy: i32,
+) -> Vec<(u32, u32)> {
+    let color = board[x as usize][y as usize];
+    let mut line = vec![(x as u32, y as u32)];
+    for sign in [1, -1] {
+        let (mut nx, mut ny) = (x + dx * sign, y + dy * sign);
+        while get_cell(board, nx, ny) == Some(color) {
+            line.push((nx as u32, ny as u32));
+            nx += dx * sign;
+            ny += dy * sign;
         }
     }
+    line.sort();
+    line
+}
+
+/// 取得 `(x, y)` 处的值，超出棋盘返回 `None`。
+pub(crate) fn get_cell(board: &[Vec<i32>], x: i32, y: i32) -> Option<i32> {
+    if x < 0 || y < 0 {
+        return None;
+    }
+    board.get(x as usize)?.get(y as usize).copied()
+}
+
+/// 摆棋时棋子在棋盘数组中的值：-1 为空，0 为黑，1 为白。
+pub(crate) fn piece_value(piece: Option<Player>) -> i32 {
+    match piece {
+        None => -1,
+        Some(Player::Black) => 0,
+        Some(Player::White) => 1,
+    }
+}
+
+/// 每个格子上棋子所属的玩家，与 `piece_value` 相反。
+pub(crate) fn board_owners(board: &[Vec<i32>]) -> Vec<Vec<Option<Player>>> {
+    board
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|&cell| match cell {
+                    0 => Some(Player::Black),
+                    1 => Some(Player::White),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
 }
 
 impl ChessboardTrait for Gomoku {
     fn get_length(&self) -> (u32, u32) {
-        (15, 15)
+        (self.size_x, self.size_y)
     }
     fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
         let black = (Color::BLACK, Color::WHITE);
@@ -39,35 +185,78 @@ impl ChessboardTrait for Gomoku {
             .collect()
     }
     fn go(&mut self, x: i32, y: i32) -> Option<String> {
-        if x < 0 || x > 14 || y < 0 || y > 14 {
+        if x < 0 || x >= self.size_x as i32 || y < 0 || y >= self.size_y as i32 {
             return None;
         }
-        if self.board[x as usize][y as usize] != -1 {
+        if self.result.is_some() || self.board[x as usize][y as usize] != -1 {
+            return None;
+        }
+        if self.forbidden.contains(&(x as u32, y as u32)) {
             return None;
         }
         self.board[x as usize][y as usize] = self.current_player;
+        let winner = self.get_player();
+        if let Some(line) = self.find_five(x, y) {
+            self.result = Some(GameResult {
+                winner: Some(winner),
+                line,
+            });
+        } else if self.board.iter().flatten().all(|&cell| cell != -1) {
+            self.result = Some(GameResult {
+                winner: None,
+                line: vec![],
+            });
+        }
         let p = if self.current_player == 0 { "B" } else { "W" };
         self.current_player = 1 - self.current_player;
-        let mut p_x = x;
-        if p_x >= 'I' as i32 - 'A' as i32 {
-            p_x = p_x + 1;
-        }
-        Some(format!(
-            "play {} {}{}",
-            p,
-            ('A' as i32 + p_x) as u8 as char,
-            15 - y
-        ))
+        self.update_forbidden();
+        Some(format!("play {} {}", p, get_coord(x, y, self.size_y)))
     }
     fn new_board(&mut self) {
-        self.board = vec![vec![-1; 15]; 15];
+        self.board = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
         self.current_player = 0;
+        self.result = None;
+        self.update_forbidden();
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        if get_cell(&self.board, x, y).is_none() {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        self.result = None;
+        self.update_forbidden();
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
+        self.update_forbidden();
     }
-    fn get_player(&self) -> super::chessboard_trait::Player {
+    fn get_player(&self) -> Player {
         if self.current_player == 1 {
-            super::chessboard_trait::Player::White
+            Player::White
         } else {
-            super::chessboard_trait::Player::Black
+            Player::Black
+        }
+    }
+    fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
+    fn get_marks(&self) -> Vec<(u32, u32, Mark)> {
+        self.forbidden
+            .iter()
+            .map(|&(x, y)| (x, y, Mark::Cross(Color::from_rgb8(241, 9, 9))))
+            .collect()
+    }
+    fn get_rules(&self) -> Option<String> {
+        match self.rule {
+            GomokuRule::Freestyle => None,
+            GomokuRule::Standard => Some("standard".to_string()),
+            GomokuRule::Renju => Some("renju".to_string()),
+            GomokuRule::Caro => Some("caro".to_string()),
+            GomokuRule::Omok => Some("omok".to_string()),
         }
     }
 }
@@ -0,0 +1,183 @@
+//! 重力棋：棋子落到所在列最下方的空位，例如四子棋（Connect Four）和重力五子棋。
+
+use iced::Color;
+
+use crate::chessboard::{
+    chessboard_trait::{ChessboardTrait, GameResult, Player},
+    get_coord,
+    gomoku::{DIRECTIONS, board_owners, get_cell, line_through, piece_value},
+};
+
+pub struct Gravity {
+    // -1: empty 0: black 1: white
+    board: Vec<Vec<i32>>,
+    size_x: u32,
+    size_y: u32,
+    current_player: i32,
+    result: Option<GameResult>,
+    //连成几子获胜
+    goal: usize,
+}
+
+impl Gravity {
+    pub fn new(goal: usize, width: u32, height: u32) -> Self {
+        Gravity {
+            board: vec![vec![-1; height as usize]; width as usize],
+            size_x: width,
+            size_y: height,
+            current_player: 0,
+            result: None,
+            goal,
+        }
+    }
+
+    //第 x 列最下方的空位
+    fn landing(&self, x: i32) -> Option<i32> {
+        let column = self.board.get(usize::try_from(x).ok()?)?;
+        column
+            .iter()
+            .rposition(|&cell| cell == -1)
+            .map(|y| y as i32)
+    }
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Self::new(4, 7, 6)
+    }
+}
+
+impl ChessboardTrait for Gravity {
+    fn get_length(&self) -> (u32, u32) {
+        (self.size_x, self.size_y)
+    }
+    fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
+        let black = (Color::BLACK, Color::WHITE);
+        let white = (Color::WHITE, Color::BLACK);
+        self.board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| match cell {
+                        0 => Some(black),
+                        1 => Some(white),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    fn resolve(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        get_cell(&self.board, x, y)?;
+        Some((x, self.landing(x)?))
+    }
+    fn go(&mut self, x: i32, y: i32) -> Option<String> {
+        if self.result.is_some() || self.landing(x) != Some(y) {
+            return None;
+        }
+        self.board[x as usize][y as usize] = self.current_player;
+        let winner = self.get_player();
+        if let Some(line) = DIRECTIONS
+            .iter()
+            .map(|&(dx, dy)| line_through(&self.board, x, y, dx, dy))
+            .find(|line| line.len() >= self.goal)
+        {
+            self.result = Some(GameResult {
+                winner: Some(winner),
+                line,
+            });
+        } else if self.board.iter().flatten().all(|&cell| cell != -1) {
+            self.result = Some(GameResult {
+                winner: None,
+                line: vec![],
+            });
+        }
+        let p = if self.current_player == 0 { "B" } else { "W" };
+        self.current_player = 1 - self.current_player;
+        Some(format!("play {} {}", p, get_coord(x, y, self.size_y)))
+    }
+    fn new_board(&mut self) {
+        self.board = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
+        self.current_player = 0;
+        self.result = None;
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    /// 只能在该列最下方的空位摆子，或者清除该列最上面的棋子，保证棋子不会悬空。
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        let Some(cell) = get_cell(&self.board, x, y) else {
+            return false;
+        };
+        let landing = self.landing(x);
+        let allowed = match piece {
+            //覆盖已有的棋子只改变颜色，不会悬空
+            Some(_) => cell != -1 || landing == Some(y),
+            None => landing.map_or(0, |l| l + 1) == y,
+        };
+        if !allowed {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        self.result = None;
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
+    }
+    fn get_player(&self) -> Player {
+        if self.current_player == 1 {
+            Player::White
+        } else {
+            Player::Black
+        }
+    }
+    fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_land_on_the_lowest_empty_cell() {
+        let mut board = Gravity::new(4, 7, 6);
+        //点击该列任意一格都落到最下方
+        assert_eq!(board.resolve(3, 0), Some((3, 5)));
+        assert_eq!(board.go(3, 5), Some("play B D1".to_string()));
+        assert_eq!(board.resolve(3, 2), Some((3, 4)));
+        //悬空的位置不能落子
+        assert!(board.go(3, 0).is_none());
+        assert_eq!(board.go(3, 4), Some("play W D2".to_string()));
+        assert_eq!(board.resolve(4, 4), Some((4, 5)));
+        assert_eq!(board.resolve(7, 0), None);
+    }
+
+    #[test]
+    fn full_column_is_rejected() {
+        let mut board = Gravity::new(4, 7, 6);
+        for y in (0..6).rev() {
+            assert!(board.go(0, y).is_some());
+        }
+        assert_eq!(board.resolve(0, 3), None);
+        assert!(board.go(0, 0).is_none());
+        assert!(board.go(0, -1).is_none());
+        assert_eq!(board.get_player(), Player::Black);
+    }
+
+    #[test]
+    fn connect_four_wins() {
+        let mut board = Gravity::new(4, 7, 6);
+        for x in 0..3 {
+            board.go(x, 5);
+            board.go(x, 4);
+        }
+        assert!(board.get_result().is_none());
+        board.go(3, 5);
+        let result = board.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::Black));
+        assert_eq!(result.line.len(), 4);
+    }
+}
@@ -0,0 +1,317 @@
+//! 六贯棋（Hex）：黑方连接上下两边，白方连接左右两边，先连通者获胜。
+//!
+//! 坐标使用 HexGui、MoHex 的约定：列为小写字母（不跳过 `i`），行号从上往下从 1 开始，
+//! 左上角为 `a1`。
+
+use std::collections::VecDeque;
+
+use iced::Color;
+
+use crate::chessboard::{
+    Move,
+    chessboard_trait::{BoardShape, ChessboardTrait, GameResult, Player},
+    gomoku::{board_owners, get_cell, piece_value},
+};
+
+//六边形格子的六个邻居，见 `BoardShape::Hex`
+const NEIGHBORS: [(i32, i32); 6] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, -1), (-1, 1)];
+
+pub struct Hex {
+    // -1: empty 0: black 1: white
+    board: Vec<Vec<i32>>,
+    size_x: u32,
+    size_y: u32,
+    current_player: i32,
+    result: Option<GameResult>,
+    //已经下的手数
+    moves: u32,
+}
+
+impl Hex {
+    pub fn new(width: u32, height: u32) -> Self {
+        Hex {
+            board: vec![vec![-1; height as usize]; width as usize],
+            size_x: width,
+            size_y: height,
+            current_player: 0,
+            result: None,
+            moves: 0,
+        }
+    }
+
+    //color 是否已经连通自己的两条边，连通时返回最短的一条连线
+    fn connection(&self, color: i32) -> Option<Vec<(u32, u32)>> {
+        let (w, h) = (self.size_x as i32, self.size_y as i32);
+        //黑方从上边走到下边，白方从左边走到右边
+        let starts = if color == 0 {
+            (0..w).map(|x| (x, 0)).collect::<Vec<_>>()
+        } else {
+            (0..h).map(|y| (0, y)).collect()
+        };
+        let is_goal = |(x, y): (i32, i32)| if color == 0 { y == h - 1 } else { x == w - 1 };
+
+        let mut parent = vec![vec![None; h as usize]; w as usize];
+        let mut queue = VecDeque::new();
+        for (x, y) in starts {
+            if self.board[x as usize][y as usize] == color {
+                parent[x as usize][y as usize] = Some((x, y));
+                queue.push_back((x, y));
+            }
+        }
+        while let Some((x, y)) = queue.pop_front() {
+            if is_goal((x, y)) {
+                let mut line = vec![(x as u32, y as u32)];
+                let (mut cx, mut cy) = (x, y);
+                while let Some((px, py)) = parent[cx as usize][cy as usize]
+                    && (px, py) != (cx, cy)
+                {
+                    line.push((px as u32, py as u32));
+                    (cx, cy) = (px, py);
+                }
+                line.reverse();
+                return Some(line);
+            }
+            for (dx, dy) in NEIGHBORS {
+                let (nx, ny) = (x + dx, y + dy);
+                if get_cell(&self.board, nx, ny) == Some(color)
+                    && parent[nx as usize][ny as usize].is_none()
+                {
+                    parent[nx as usize][ny as usize] = Some((x, y));
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// 六贯棋的列名，例如第 9 列为 `i`。
+///
+/// ```
+/// use rboard::chessboard::hex::column_name;
+///
+/// assert_eq!(column_name(0), "a");
+/// assert_eq!(column_name(8), "i");
+/// ```
+pub fn column_name(x: i32) -> String {
+    ((b'a' + x as u8) as char).to_string()
+}
+
+/// 六贯棋的坐标，例如 `(8, 0)` 为 `i1`。
+pub fn hex_coord(x: i32, y: i32) -> String {
+    format!("{}{}", column_name(x), y + 1)
+}
+
+/// 解析六贯棋的坐标（不区分大小写），超出 `w * h` 棋盘时返回 `None`。
+///
+/// ```
+/// use rboard::chessboard::hex::parse_hex_coord;
+///
+/// assert_eq!(parse_hex_coord("i1", 11, 11), Some((8, 0)));
+/// assert_eq!(parse_hex_coord("K11", 11, 11), Some((10, 10)));
+/// assert_eq!(parse_hex_coord("l1", 11, 11), None);
+/// ```
+pub fn parse_hex_coord(s: &str, w: u32, h: u32) -> Option<(u32, u32)> {
+    let s = s.trim().to_ascii_lowercase();
+    let mut chars = s.chars();
+    let column = chars.next().filter(|c| c.is_ascii_lowercase())?;
+    let x = column as u32 - 'a' as u32;
+    let y = chars.as_str().parse::<u32>().ok()?.checked_sub(1)?;
+    (x < w && y < h).then_some((x, y))
+}
+
+impl Default for Hex {
+    fn default() -> Self {
+        Self::new(11, 11)
+    }
+}
+
+impl ChessboardTrait for Hex {
+    fn get_length(&self) -> (u32, u32) {
+        (self.size_x, self.size_y)
+    }
+    fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
+        let black = (Color::BLACK, Color::WHITE);
+        let white = (Color::WHITE, Color::BLACK);
+        self.board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| match cell {
+                        0 => Some(black),
+                        1 => Some(white),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// 交换规则：白方的第一手点击黑方的第一子即为交换，
+    /// 该子沿对角线翻转为白子（`(x, y)` 变为 `(y, x)`），之后轮到黑方，
+    /// 返回 `"play W swap-pieces"`。只有正方形棋盘可以交换。
+    fn go(&mut self, x: i32, y: i32) -> Option<String> {
+        let cell = get_cell(&self.board, x, y)?;
+        if self.result.is_some() {
+            return None;
+        }
+        let cmd = if cell == 0 && self.moves == 1 && self.size_x == self.size_y {
+            self.board[x as usize][y as usize] = -1;
+            self.board[y as usize][x as usize] = 1;
+            "play W swap-pieces".to_string()
+        } else if cell == -1 {
+            self.board[x as usize][y as usize] = self.current_player;
+            let p = if self.current_player == 0 { "B" } else { "W" };
+            format!("play {} {}", p, hex_coord(x, y))
+        } else {
+            return None;
+        };
+        if let Some(line) = self.connection(self.current_player) {
+            self.result = Some(GameResult {
+                winner: Some(self.get_player()),
+                line,
+            });
+        }
+        self.moves += 1;
+        self.current_player = 1 - self.current_player;
+        Some(cmd)
+    }
+    fn new_board(&mut self) {
+        self.board = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
+        self.current_player = 0;
+        self.result = None;
+        self.moves = 0;
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        if get_cell(&self.board, x, y).is_none() {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        self.result = None;
+        //按棋盘上的子数计算手数，只有一子时白方可以交换
+        self.moves = self
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell != -1)
+            .count() as u32;
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
+    }
+    fn get_player(&self) -> Player {
+        if self.current_player == 1 {
+            Player::White
+        } else {
+            Player::Black
+        }
+    }
+    fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
+    fn get_shape(&self) -> BoardShape {
+        BoardShape::Hex
+    }
+    fn get_coord(&self, x: i32, y: i32) -> String {
+        hex_coord(x, y)
+    }
+    /// 引擎的 `swap-pieces` 换算成点击黑方的第一子，见 `go`。
+    fn get_move(&self, s: &str) -> Option<Move> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("swap-pieces") {
+            if self.moves != 1 {
+                return None;
+            }
+            let (x, y) = (0..self.size_x)
+                .flat_map(|x| (0..self.size_y).map(move |y| (x, y)))
+                .find(|&(x, y)| self.board[x as usize][y as usize] == 0)?;
+            return Some(Move::Place(x, y));
+        }
+        if s.eq_ignore_ascii_case("resign") {
+            return Some(Move::Resign);
+        }
+        let (x, y) = parse_hex_coord(s, self.size_x, self.size_y)?;
+        Some(Move::Place(x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_include_i() {
+        let mut hex = Hex::new(11, 11);
+        assert_eq!(hex.go(8, 0), Some("play B i1".to_string()));
+        assert_eq!(hex.go(10, 10), Some("play W k11".to_string()));
+        assert_eq!(hex.get_move("J3"), Some(Move::Place(9, 2)));
+        assert_eq!(hex.get_move("resign"), Some(Move::Resign));
+        assert_eq!(hex.get_move("a12"), None);
+        assert_eq!(hex.get_move("a0"), None);
+    }
+
+    #[test]
+    fn black_connects_top_and_bottom() {
+        let mut hex = Hex::new(5, 5);
+        for y in 0..5 {
+            assert!(hex.get_result().is_none());
+            hex.go(0, y);
+            if y < 4 {
+                hex.go(4, y);
+            }
+        }
+        let result = hex.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::Black));
+        assert_eq!(result.line, (0..5).map(|y| (0, y)).collect::<Vec<_>>());
+        assert!(hex.go(2, 2).is_none());
+    }
+
+    #[test]
+    fn white_connects_left_and_right() {
+        let mut hex = Hex::new(5, 5);
+        for (x, y) in [(0, 4), (1, 3), (2, 2), (3, 1)] {
+            hex.set_piece(x, y, Some(Player::White));
+        }
+        hex.set_player(Player::White);
+        assert!(hex.get_result().is_none());
+        hex.go(4, 0);
+        let result = hex.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::White));
+        assert_eq!(result.line.len(), 5);
+    }
+
+    #[test]
+    fn other_diagonal_is_not_adjacent() {
+        let mut hex = Hex::new(5, 5);
+        for i in 0..4 {
+            hex.set_piece(i, i, Some(Player::Black));
+        }
+        hex.set_player(Player::Black);
+        hex.go(4, 4);
+        assert!(hex.get_result().is_none());
+    }
+
+    #[test]
+    fn swap() {
+        let mut hex = Hex::new(11, 11);
+        hex.go(2, 5);
+        //白方点击黑方的第一子即为交换，棋子沿对角线翻转为白子
+        assert_eq!(hex.get_move("swap-pieces"), Some(Move::Place(2, 5)));
+        assert_eq!(hex.go(2, 5), Some("play W swap-pieces".to_string()));
+        assert_eq!(hex.board[2][5], -1);
+        assert_eq!(hex.board[5][2], 1);
+        assert_eq!(hex.get_player(), Player::Black);
+        //之后不能再交换
+        assert_eq!(hex.get_move("swap-pieces"), None);
+        hex.go(3, 3);
+        assert!(hex.go(3, 3).is_none());
+
+        //长方形棋盘不能交换
+        let mut hex = Hex::new(11, 9);
+        hex.go(2, 5);
+        assert!(hex.go(2, 5).is_none());
+    }
+}
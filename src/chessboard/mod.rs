@@ -1,28 +1,83 @@
 pub mod chessboard_trait;
+pub mod connect6;
 pub mod gomoku;
+pub mod gravity;
+pub mod hex;
+pub mod othello;
+pub mod pente;
+pub mod renju;
+pub mod weiqi;
 pub mod zhenqi;
 
-use crate::chessboard::chessboard_trait::ChessboardTrait;
+use crate::chessboard::{
+    chessboard_trait::ChessboardTrait, gomoku::GomokuRule, pente::PenteRule, weiqi::GoRules,
+};
+
+/// 棋盘边长的取值范围，列坐标超过 25 时使用两个字母（`AA`、`AB`……）。
+pub const MIN_BOARD_SIZE: u32 = 5;
+pub const MAX_BOARD_SIZE: u32 = 26;
 
 /// 根据给定名称创建并返回对应的棋盘实例。
 ///
 /// # 参数
 /// - `name`: 棋盘类型的唯一标识字符串，例如 `"gomoku"`等。
 ///   - 如果传入的 `name` 不在支持的列表中，当前实现会默认返回 `"gomoku"` 类型的棋盘实例。
+/// - `size`: 棋盘的（宽度，高度），为 `None` 时使用该棋盘的默认尺寸，见 `get_default_size`。
 ///
 /// # 返回值
 /// 返回一个 `Box<dyn ChessboardTrait>`，即指向实现了 `ChessboardTrait` 的具体棋盘对象的堆分配指针。
 ///
 /// # 示例
 /// ```
-/// let board = get_chessboard("gomoku".to_string());
-/// // board: Box<dyn ChessboardTrait>
+/// use rboard::chessboard::get_chessboard;
+///
+/// let board = get_chessboard("gomoku".to_string(), Some((19, 19)));
+/// assert_eq!(board.get_length(), (19, 19));
 /// ```
-pub fn get_chessboard(name: String) -> Box<dyn ChessboardTrait> {
+pub fn get_chessboard(name: String, size: Option<(u32, u32)>) -> Box<dyn ChessboardTrait> {
+    let (w, h) = size.unwrap_or_else(|| get_default_size(&name));
     match name.as_str() {
-        "gomoku" => Box::new(gomoku::Gomoku::new()),
-        "zhenqi" => Box::new(zhenqi::Zhenqi::new()),
-        _ => Box::new(gomoku::Gomoku::new()),
+        "gomoku" => Box::new(gomoku::Gomoku::new(GomokuRule::Freestyle, w, h)),
+        "zhenqi" => Box::new(zhenqi::Zhenqi::new(w, h)),
+        "gomoku_standard" => Box::new(gomoku::Gomoku::new(GomokuRule::Standard, w, h)),
+        "renju" => Box::new(gomoku::Gomoku::new(GomokuRule::Renju, w, h)),
+        "caro" => Box::new(gomoku::Gomoku::new(GomokuRule::Caro, w, h)),
+        "omok" => Box::new(gomoku::Gomoku::new(GomokuRule::Omok, w, h)),
+        "go" => Box::new(weiqi::Weiqi::new(GoRules::chinese(), w, h)),
+        "go_japanese" => Box::new(weiqi::Weiqi::new(GoRules::japanese(), w, h)),
+        "go_tromp_taylor" => Box::new(weiqi::Weiqi::new(GoRules::tromp_taylor(), w, h)),
+        "othello" | "othello_6" | "othello_10" => Box::new(othello::Othello::new(w, h)),
+        "connect6" => Box::new(connect6::Connect6::new(w, h)),
+        "pente" => Box::new(pente::Pente::new(PenteRule::Pente, w, h)),
+        "keryo_pente" => Box::new(pente::Pente::new(PenteRule::Keryo, w, h)),
+        "hex" | "hex_13" | "hex_19" => Box::new(hex::Hex::new(w, h)),
+        "connect_four" => Box::new(gravity::Gravity::new(4, w, h)),
+        "gravity_gomoku" => Box::new(gravity::Gravity::new(5, w, h)),
+        _ => Box::new(gomoku::Gomoku::new(GomokuRule::Freestyle, w, h)),
+    }
+}
+
+/// 是否为五子棋类的棋盘（自由五子棋、标准五子棋、连珠、Caro、Omok），它们可以共用五子棋的棋谱。
+pub fn is_gomoku(name: &str) -> bool {
+    matches!(
+        name,
+        "gomoku" | "gomoku_standard" | "renju" | "caro" | "omok"
+    )
+}
+
+/// 获取棋盘类型的默认尺寸（宽度，高度）。
+pub fn get_default_size(name: &str) -> (u32, u32) {
+    match name {
+        "zhenqi" => (8, 8),
+        "go" | "go_japanese" | "go_tromp_taylor" | "connect6" | "pente" | "keryo_pente" => (19, 19),
+        "othello" => (8, 8),
+        "othello_6" => (6, 6),
+        "othello_10" => (10, 10),
+        "hex" => (11, 11),
+        "connect_four" => (7, 6),
+        "hex_13" => (13, 13),
+        "hex_19" => (19, 19),
+        _ => (15, 15),
     }
 }
 
@@ -35,6 +90,8 @@ pub fn get_chessboard(name: String) -> Box<dyn ChessboardTrait> {
 ///
 /// # 示例
 /// ```
+/// use rboard::chessboard::get_all_board_names;
+///
 /// let names = get_all_board_names();
 /// for (display, key) in names {
 ///     println!("显示名称: {}, 唯一标识: {}", display, key);
@@ -44,9 +101,88 @@ pub fn get_all_board_names() -> Vec<(String, String)> {
     vec![
         ("Gomoku 15 * 15".to_string(), "gomoku".to_string()),
         ("Zhenqi 8 * 8".to_string(), "zhenqi".to_string()),
+        (
+            "Gomoku Standard 15 * 15".to_string(),
+            "gomoku_standard".to_string(),
+        ),
+        ("Renju 15 * 15".to_string(), "renju".to_string()),
+        ("Caro 15 * 15".to_string(), "caro".to_string()),
+        ("Omok 15 * 15".to_string(), "omok".to_string()),
+        ("Go 19 * 19 Chinese".to_string(), "go".to_string()),
+        ("Go 19 * 19 Japanese".to_string(), "go_japanese".to_string()),
+        (
+            "Go 19 * 19 Tromp-Taylor".to_string(),
+            "go_tromp_taylor".to_string(),
+        ),
+        ("Othello 8 * 8".to_string(), "othello".to_string()),
+        ("Othello 6 * 6".to_string(), "othello_6".to_string()),
+        ("Othello 10 * 10".to_string(), "othello_10".to_string()),
+        ("Connect6 19 * 19".to_string(), "connect6".to_string()),
+        ("Pente 19 * 19".to_string(), "pente".to_string()),
+        ("Keryo-Pente 19 * 19".to_string(), "keryo_pente".to_string()),
+        ("Hex 11 * 11".to_string(), "hex".to_string()),
+        ("Hex 13 * 13".to_string(), "hex_13".to_string()),
+        ("Hex 19 * 19".to_string(), "hex_19".to_string()),
+        ("Connect Four 7 * 6".to_string(), "connect_four".to_string()),
+        (
+            "Gravity Gomoku 15 * 15".to_string(),
+            "gravity_gomoku".to_string(),
+        ),
     ]
 }
 
+//列坐标转字母，跳过字母 I，超过 Z 后使用两个字母，例如:0 -> A, 8 -> J, 25 -> AA
+pub fn get_column_name(x: i32) -> String {
+    const COLUMNS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+    let len = COLUMNS.len() as i32;
+    if x < len {
+        (COLUMNS[x as usize] as char).to_string()
+    } else {
+        format!(
+            "{}{}",
+            COLUMNS[(x / len - 1) as usize] as char,
+            COLUMNS[(x % len) as usize] as char
+        )
+    }
+}
+
+//坐标转棋子 例如:(1, y - 8) -> B8，跳过字母 I
+pub fn get_coord(x: i32, y: i32, size_y: u32) -> String {
+    format!("{}{}", get_column_name(x), size_y as i32 - y)
+}
+
+/// 引擎给出的一手棋。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Move {
+    /// 落子，坐标与 `get_piece` 相同
+    Place(u32, u32),
+    Pass,
+    Resign,
+}
+
+/// 解析引擎给出的一手棋，除了坐标之外还支持 `pass` 和 `resign`（不区分大小写），
+/// 超出 `x * y` 棋盘的坐标返回 `None`。
+///
+/// # 示例
+/// ```
+/// use rboard::chessboard::{Move, get_move};
+///
+/// assert_eq!(get_move("pass", 15, 15), Some(Move::Pass));
+/// assert_eq!(get_move("H8", 15, 15), Some(Move::Place(7, 7)));
+/// assert_eq!(get_move("Q16", 15, 15), None);
+/// ```
+pub fn get_move(s: &str, x: u32, y: u32) -> Option<Move> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("pass") {
+        Some(Move::Pass)
+    } else if s.eq_ignore_ascii_case("resign") {
+        Some(Move::Resign)
+    } else {
+        let (px, py) = get_piece(&s.to_uppercase(), x, y, true)?;
+        (px < x && py < y).then_some(Move::Place(px, py))
+    }
+}
+
 //棋子转坐标 例如:B8 -> 1 , (y - 8)
 pub fn get_piece(size: &String, _x: u32, y: u32, ingore_i: bool) -> Option<(u32, u32)> {
     if *size == "pass".to_string() {
@@ -55,30 +191,27 @@ pub fn get_piece(size: &String, _x: u32, y: u32, ingore_i: bool) -> Option<(u32,
     if size.len() < 2 {
         return None;
     }
-    let mut chars = size.chars();
-
-    let mut x1 = 9999;
-    let mut y1 = 9999;
-    if let Some(c) = chars.next() {
-        x1 = c as u32 - 'A' as u32;
-        if ingore_i && x1 >= 'I' as u32 - 'A' as u32 {
-            x1 = x1 - 1;
-        }
-    }
-    while let Some(c) = chars.next() {
-        if let Some(num) = c.to_digit(10) {
-            if y1 == 9999 {
-                y1 = num;
-            } else {
-                y1 = y1 * 10 + num;
+    let letters = size
+        .chars()
+        .take_while(|c| c.is_ascii_uppercase())
+        .map(|c| {
+            let mut x1 = c as u32 - 'A' as u32;
+            if ingore_i && x1 >= 'I' as u32 - 'A' as u32 {
+                x1 -= 1;
             }
-        } else {
-            break;
-        }
-    }
-    if let Some(y) = y.checked_sub(y1) {
-        Some((x1, y))
-    } else {
-        None
-    }
+            x1
+        })
+        .collect::<Vec<u32>>();
+    let x1 = match letters[..] {
+        [x1] => x1,
+        [x0, x1] => (x0 + 1) * 25 + x1,
+        _ => return None,
+    };
+    let y1 = size[letters.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse::<u32>()
+        .ok()?;
+    y.checked_sub(y1).map(|y| (x1, y))
 }
@@ -0,0 +1,307 @@
+//! 黑白棋（奥赛罗）：夹住翻转、无棋可走时停一手、终局数子。
+
+use iced::Color;
+
+use crate::chessboard::{
+    chessboard_trait::{ChessboardTrait, GameResult, Mark, Player},
+    get_coord,
+    gomoku::{board_owners, get_cell, piece_value},
+};
+
+const DIRECTIONS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+pub struct Othello {
+    // -1: empty 0: black 1: white
+    board: Vec<Vec<i32>>,
+    size_x: u32,
+    size_y: u32,
+    current_player: i32,
+    result: Option<GameResult>,
+    //当前玩家可以落子的位置
+    legal: Vec<(u32, u32)>,
+}
+
+impl Othello {
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut othello = Othello {
+            board: vec![],
+            size_x: width,
+            size_y: height,
+            current_player: 0,
+            result: None,
+            legal: vec![],
+        };
+        othello.new_board();
+        othello
+    }
+
+    //color 在 (x, y) 落子后会被翻转的棋子
+    fn flips(&self, x: i32, y: i32, color: i32) -> Vec<(i32, i32)> {
+        if get_cell(&self.board, x, y) != Some(-1) {
+            return vec![];
+        }
+        let mut flips = vec![];
+        for (dx, dy) in DIRECTIONS {
+            let mut line = vec![];
+            let (mut nx, mut ny) = (x + dx, y + dy);
+            while get_cell(&self.board, nx, ny) == Some(1 - color) {
+                line.push((nx, ny));
+                nx += dx;
+                ny += dy;
+            }
+            if !line.is_empty() && get_cell(&self.board, nx, ny) == Some(color) {
+                flips.extend(line);
+            }
+        }
+        flips
+    }
+
+    fn legal_moves(&self, color: i32) -> Vec<(u32, u32)> {
+        let mut moves = vec![];
+        for x in 0..self.size_x {
+            for y in 0..self.size_y {
+                if !self.flips(x as i32, y as i32, color).is_empty() {
+                    moves.push((x, y));
+                }
+            }
+        }
+        moves
+    }
+
+    /// 双方的棋子数（黑，白）。
+    pub fn count(&self) -> (usize, usize) {
+        let cells = self.board.iter().flatten();
+        (
+            cells.clone().filter(|&&c| c == 0).count(),
+            cells.filter(|&&c| c == 1).count(),
+        )
+    }
+
+    //更新当前玩家的合法落子；双方都无棋可走时按棋子数判断胜负
+    fn update_legal(&mut self) {
+        self.legal = self.legal_moves(self.current_player);
+        if self.legal.is_empty() && self.legal_moves(1 - self.current_player).is_empty() {
+            let (black, white) = self.count();
+            let winner = match black.cmp(&white) {
+                std::cmp::Ordering::Greater => Some(Player::Black),
+                std::cmp::Ordering::Less => Some(Player::White),
+                std::cmp::Ordering::Equal => None,
+            };
+            self.result = Some(GameResult {
+                winner,
+                line: vec![],
+            });
+        }
+    }
+
+    fn player_name(&self) -> &'static str {
+        if self.current_player == 0 { "B" } else { "W" }
+    }
+}
+
+impl Default for Othello {
+    fn default() -> Self {
+        Self::new(8, 8)
+    }
+}
+
+impl ChessboardTrait for Othello {
+    fn get_length(&self) -> (u32, u32) {
+        (self.size_x, self.size_y)
+    }
+    fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
+        let black = (Color::BLACK, Color::WHITE);
+        let white = (Color::WHITE, Color::BLACK);
+        self.board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| match cell {
+                        0 => Some(black),
+                        1 => Some(white),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    fn go(&mut self, x: i32, y: i32) -> Option<String> {
+        if self.result.is_some() {
+            return None;
+        }
+        let flips = self.flips(x, y, self.current_player);
+        if flips.is_empty() {
+            return None;
+        }
+        self.board[x as usize][y as usize] = self.current_player;
+        for (fx, fy) in flips {
+            self.board[fx as usize][fy as usize] = self.current_player;
+        }
+        let p = self.player_name();
+        self.current_player = 1 - self.current_player;
+        self.update_legal();
+        Some(format!("play {} {}", p, get_coord(x, y, self.size_y)))
+    }
+    fn pass(&mut self) -> Option<String> {
+        if !self.must_pass() {
+            return None;
+        }
+        let p = self.player_name();
+        self.current_player = 1 - self.current_player;
+        self.update_legal();
+        Some(format!("play {} pass", p))
+    }
+    fn must_pass(&self) -> bool {
+        self.result.is_none() && self.legal.is_empty()
+    }
+    fn new_board(&mut self) {
+        let (w, h) = (self.size_x as usize, self.size_y as usize);
+        self.board = vec![vec![-1; h]; w];
+        let (cx, cy) = (w / 2, h / 2);
+        if cx > 0 && cy > 0 {
+            self.board[cx - 1][cy - 1] = 1;
+            self.board[cx][cy] = 1;
+            self.board[cx][cy - 1] = 0;
+            self.board[cx - 1][cy] = 0;
+        }
+        self.current_player = 0;
+        self.result = None;
+        self.update_legal();
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        if get_cell(&self.board, x, y).is_none() {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        self.result = None;
+        self.update_legal();
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
+        self.result = None;
+        self.update_legal();
+    }
+    fn get_player(&self) -> Player {
+        if self.current_player == 1 {
+            Player::White
+        } else {
+            Player::Black
+        }
+    }
+    fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
+    fn get_marks(&self) -> Vec<(u32, u32, Mark)> {
+        let color = if self.current_player == 0 {
+            Color::from_rgba8(0, 0, 0, 0.5)
+        } else {
+            Color::from_rgba8(255, 255, 255, 0.7)
+        };
+        self.legal
+            .iter()
+            .map(|&(x, y)| (x, y, Mark::Dot(color)))
+            .collect()
+    }
+    fn get_info(&self) -> Option<String> {
+        let (black, white) = self.count();
+        Some(format!("黑 {}，白 {}", black, white))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //8 * 8 的空棋盘摆上黑子和白子，轮到 `player`
+    fn position(black: &[(i32, i32)], white: &[(i32, i32)], player: Player) -> Othello {
+        let mut othello = Othello::new(8, 8);
+        othello.board = vec![vec![-1; 8]; 8];
+        for &(x, y) in black {
+            othello.board[x as usize][y as usize] = 0;
+        }
+        for &(x, y) in white {
+            othello.board[x as usize][y as usize] = 1;
+        }
+        othello.set_player(player);
+        othello
+    }
+
+    #[test]
+    fn opening_position() {
+        let othello = Othello::new(8, 8);
+        assert_eq!(othello.count(), (2, 2));
+        assert_eq!(othello.get_player(), Player::Black);
+        let mut legal = othello.legal.clone();
+        legal.sort();
+        assert_eq!(legal, vec![(2, 3), (3, 2), (4, 5), (5, 4)]);
+        assert!(othello.get_result().is_none());
+    }
+
+    #[test]
+    fn flips_in_all_directions() {
+        let white = DIRECTIONS.map(|(dx, dy)| (3 + dx, 3 + dy));
+        let black = DIRECTIONS.map(|(dx, dy)| (3 + 2 * dx, 3 + 2 * dy));
+        let mut othello = position(&black, &white, Player::Black);
+        assert_eq!(othello.go(3, 3), Some("play B D5".to_string()));
+        for (x, y) in white {
+            assert_eq!(othello.board[x as usize][y as usize], 0);
+        }
+        assert_eq!(othello.count(), (17, 0));
+    }
+
+    #[test]
+    fn only_closed_lines_flip() {
+        //(4, 3) 到 (5, 3) 的白子另一端没有黑子，(3, 4) 被 (3, 6) 的黑子夹住
+        let mut othello = position(&[(3, 6)], &[(4, 3), (5, 3), (3, 4), (3, 5)], Player::Black);
+        assert!(othello.go(3, 2).is_none());
+        assert!(othello.go(3, 3).is_some());
+        assert_eq!(othello.board[4][3], 1);
+        assert_eq!(othello.board[5][3], 1);
+        assert_eq!(othello.board[3][4], 0);
+        assert_eq!(othello.board[3][5], 0);
+        //已有棋子的位置不能落子
+        assert!(othello.go(3, 3).is_none());
+    }
+
+    #[test]
+    fn forced_pass() {
+        //白方夹不住角上的黑子，只能停一手
+        let mut othello = position(&[(0, 0)], &[(1, 0)], Player::White);
+        assert!(othello.must_pass());
+        assert!(othello.get_result().is_none());
+        assert!(othello.go(2, 0).is_none());
+        assert_eq!(othello.pass(), Some("play W pass".to_string()));
+        assert_eq!(othello.get_player(), Player::Black);
+        assert!(!othello.must_pass());
+        //有棋可走时不能停一手
+        assert!(othello.pass().is_none());
+        assert!(othello.go(2, 0).is_some());
+        //双方都无棋可走，按棋子数判断胜负
+        let result = othello.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::Black));
+        assert!(!othello.must_pass());
+        assert!(othello.go(3, 0).is_none());
+    }
+
+    #[test]
+    fn game_ends_when_neither_side_can_move() {
+        let othello = position(&[(0, 0)], &[(7, 7)], Player::Black);
+        assert_eq!(othello.get_result().unwrap().winner, None);
+        let othello = position(&[(0, 0)], &[(7, 7), (7, 6)], Player::Black);
+        assert_eq!(othello.get_result().unwrap().winner, Some(Player::White));
+        assert_eq!(othello.get_info(), Some("黑 1，白 2".to_string()));
+    }
+}
@@ -0,0 +1,329 @@
+//! 五子棋的提子变体 Pente 与 Keryo-Pente：夹住对方棋子可以提掉，五连或提够子数获胜。
+//! 使用比赛规则：黑方第一手下在天元，第二手距离天元至少三路。
+
+use iced::Color;
+
+use crate::chessboard::{
+    chessboard_trait::{ChessboardTrait, GameResult, Mark, Player},
+    get_coord,
+    gomoku::{DIRECTIONS, board_owners, get_cell, line_through, piece_value},
+};
+
+/// Pente 的规则。
+#[derive(Clone, Copy, PartialEq)]
+pub enum PenteRule {
+    /// 夹住两子提掉，提够 10 子（5 对）获胜
+    Pente,
+    /// 夹住两子或三子提掉，提够 15 子获胜
+    Keryo,
+}
+
+pub struct Pente {
+    // -1: empty 0: black 1: white
+    board: Vec<Vec<i32>>,
+    size_x: u32,
+    size_y: u32,
+    current_player: i32,
+    result: Option<GameResult>,
+    rule: PenteRule,
+    //双方各自提走的对方棋子数
+    captures: [u32; 2],
+    //已经下的手数
+    moves: u32,
+}
+
+impl Pente {
+    pub fn new(rule: PenteRule, width: u32, height: u32) -> Self {
+        Pente {
+            board: vec![vec![-1; height as usize]; width as usize],
+            size_x: width,
+            size_y: height,
+            current_player: 0,
+            result: None,
+            rule,
+            captures: [0, 0],
+            moves: 0,
+        }
+    }
+
+    //提够多少子获胜
+    fn capture_goal(&self) -> u32 {
+        match self.rule {
+            PenteRule::Pente => 10,
+            PenteRule::Keryo => 15,
+        }
+    }
+
+    //可以被夹住提掉的连续子数
+    fn capture_lengths(&self) -> &'static [i32] {
+        match self.rule {
+            PenteRule::Pente => &[2],
+            PenteRule::Keryo => &[2, 3],
+        }
+    }
+
+    fn center(&self) -> (i32, i32) {
+        (self.size_x as i32 / 2, self.size_y as i32 / 2)
+    }
+
+    //比赛规则：黑方第一手必须下在天元，第二手必须距离天元至少三路
+    fn restricted(&self, x: i32, y: i32) -> bool {
+        let (cx, cy) = self.center();
+        match self.moves {
+            0 => (x, y) != (cx, cy),
+            2 => (x - cx).abs() < 3 && (y - cy).abs() < 3,
+            _ => false,
+        }
+    }
+
+    //(x, y) 落子后提掉被夹住的对方棋子，返回提子数
+    fn capture(&mut self, x: i32, y: i32) -> u32 {
+        let color = self.board[x as usize][y as usize];
+        let mut captured = 0;
+        for (dx, dy) in DIRECTIONS {
+            for sign in [1, -1] {
+                let (dx, dy) = (dx * sign, dy * sign);
+                for &len in self.capture_lengths() {
+                    let enclosed = (1..=len)
+                        .all(|k| get_cell(&self.board, x + dx * k, y + dy * k) == Some(1 - color));
+                    let end = get_cell(&self.board, x + dx * (len + 1), y + dy * (len + 1));
+                    if enclosed && end == Some(color) {
+                        for k in 1..=len {
+                            self.board[(x + dx * k) as usize][(y + dy * k) as usize] = -1;
+                        }
+                        captured += len as u32;
+                    }
+                }
+            }
+        }
+        captured
+    }
+}
+
+impl Default for Pente {
+    fn default() -> Self {
+        Self::new(PenteRule::Pente, 19, 19)
+    }
+}
+
+impl ChessboardTrait for Pente {
+    fn get_length(&self) -> (u32, u32) {
+        (self.size_x, self.size_y)
+    }
+    fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
+        let black = (Color::BLACK, Color::WHITE);
+        let white = (Color::WHITE, Color::BLACK);
+        self.board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| match cell {
+                        0 => Some(black),
+                        1 => Some(white),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    fn go(&mut self, x: i32, y: i32) -> Option<String> {
+        if get_cell(&self.board, x, y) != Some(-1) || self.result.is_some() {
+            return None;
+        }
+        if self.restricted(x, y) {
+            return None;
+        }
+        let color = self.current_player;
+        self.board[x as usize][y as usize] = color;
+        self.captures[color as usize] += self.capture(x, y);
+        let winner = self.get_player();
+        if let Some(line) = DIRECTIONS
+            .iter()
+            .map(|&(dx, dy)| line_through(&self.board, x, y, dx, dy))
+            .find(|line| line.len() >= 5)
+        {
+            self.result = Some(GameResult {
+                winner: Some(winner),
+                line,
+            });
+        } else if self.captures[color as usize] >= self.capture_goal() {
+            self.result = Some(GameResult {
+                winner: Some(winner),
+                line: vec![],
+            });
+        } else if self.board.iter().flatten().all(|&cell| cell != -1) {
+            self.result = Some(GameResult {
+                winner: None,
+                line: vec![],
+            });
+        }
+        self.moves += 1;
+        let p = if color == 0 { "B" } else { "W" };
+        self.current_player = 1 - self.current_player;
+        Some(format!("play {} {}", p, get_coord(x, y, self.size_y)))
+    }
+    fn new_board(&mut self) {
+        self.board = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
+        self.current_player = 0;
+        self.result = None;
+        self.captures = [0, 0];
+        self.moves = 0;
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        if get_cell(&self.board, x, y).is_none() {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        self.result = None;
+        //按棋盘上的子数计算手数，决定黑方第一手和第二手的限制是否生效
+        self.moves = self
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell != -1)
+            .count() as u32;
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
+    }
+    fn get_player(&self) -> Player {
+        if self.current_player == 1 {
+            Player::White
+        } else {
+            Player::Black
+        }
+    }
+    fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
+    fn get_marks(&self) -> Vec<(u32, u32, Mark)> {
+        //第一手只能下在天元，标出这一点而不是其余所有点
+        if self.moves == 0 {
+            let (cx, cy) = self.center();
+            return match get_cell(&self.board, cx, cy) {
+                Some(-1) => vec![(cx as u32, cy as u32, Mark::Dot(Color::from_rgb8(241, 9, 9)))],
+                _ => vec![],
+            };
+        }
+        let mut marks = vec![];
+        for x in 0..self.size_x {
+            for y in 0..self.size_y {
+                if self.board[x as usize][y as usize] == -1 && self.restricted(x as i32, y as i32) {
+                    marks.push((x, y, Mark::Cross(Color::from_rgb8(241, 9, 9))));
+                }
+            }
+        }
+        marks
+    }
+    fn get_captures(&self) -> Option<(u32, u32)> {
+        Some((self.captures[0], self.captures[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //19 * 19 棋盘上摆好棋子，轮到黑方；棋子超过两个，开局的限制不再生效
+    fn position(rule: PenteRule, black: &[(i32, i32)], white: &[(i32, i32)]) -> Pente {
+        let mut pente = Pente::new(rule, 19, 19);
+        for &(x, y) in black {
+            pente.set_piece(x, y, Some(Player::Black));
+        }
+        for &(x, y) in white {
+            pente.set_piece(x, y, Some(Player::White));
+        }
+        pente.set_player(Player::Black);
+        pente
+    }
+
+    #[test]
+    fn pente_captures_pairs_only() {
+        let mut pente = position(
+            PenteRule::Pente,
+            &[(5, 5), (5, 7)],
+            &[(6, 5), (7, 5), (6, 7), (7, 7), (8, 7)],
+        );
+        assert!(pente.go(8, 5).is_some());
+        assert_eq!(pente.board[6][5], -1);
+        assert_eq!(pente.board[7][5], -1);
+        assert_eq!(pente.get_captures(), Some((2, 0)));
+        //三子不能提
+        pente.set_player(Player::Black);
+        assert!(pente.go(9, 7).is_some());
+        assert_eq!(pente.board[7][7], 1);
+        assert_eq!(pente.get_captures(), Some((2, 0)));
+    }
+
+    #[test]
+    fn keryo_captures_pairs_and_triples() {
+        let mut pente = position(
+            PenteRule::Keryo,
+            &[(5, 5), (5, 7)],
+            &[(6, 5), (7, 5), (6, 7), (7, 7), (8, 7)],
+        );
+        assert!(pente.go(8, 5).is_some());
+        assert_eq!(pente.get_captures(), Some((2, 0)));
+        pente.set_player(Player::Black);
+        assert!(pente.go(9, 7).is_some());
+        assert!((6..=8).all(|x| pente.board[x][7] == -1));
+        assert_eq!(pente.get_captures(), Some((5, 0)));
+        //对角线方向同样可以提子
+        let mut pente = position(PenteRule::Keryo, &[(2, 2)], &[(3, 3), (4, 4), (5, 5)]);
+        pente.set_player(Player::White);
+        pente.go(0, 18);
+        assert!(pente.go(6, 6).is_some());
+        assert_eq!(pente.get_captures(), Some((3, 0)));
+    }
+
+    #[test]
+    fn capture_goal_wins() {
+        let mut pente = position(PenteRule::Pente, &[(5, 5)], &[(6, 5), (7, 5)]);
+        pente.captures = [8, 0];
+        assert!(pente.go(8, 5).is_some());
+        let result = pente.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::Black));
+        assert!(result.line.is_empty());
+
+        let mut pente = position(PenteRule::Keryo, &[(5, 5)], &[(6, 5), (7, 5), (8, 5)]);
+        pente.captures = [11, 0];
+        assert!(pente.go(9, 5).is_some());
+        assert!(pente.get_result().is_none());
+        let mut pente = position(PenteRule::Keryo, &[(5, 5)], &[(6, 5), (7, 5), (8, 5)]);
+        pente.captures = [12, 0];
+        assert!(pente.go(9, 5).is_some());
+        assert_eq!(pente.get_result().unwrap().winner, Some(Player::Black));
+    }
+
+    #[test]
+    fn five_in_a_row_wins() {
+        let mut pente = position(PenteRule::Pente, &[(0, 0), (1, 0), (2, 0), (3, 0)], &[]);
+        assert!(pente.go(4, 0).is_some());
+        assert_eq!(pente.get_result().unwrap().line.len(), 5);
+    }
+
+    #[test]
+    fn tournament_rule() {
+        let mut pente = Pente::new(PenteRule::Pente, 19, 19);
+        //第一手只能下在天元
+        assert_eq!(
+            pente.get_marks(),
+            vec![(9, 9, Mark::Dot(Color::from_rgb8(241, 9, 9)))]
+        );
+        assert!(pente.go(3, 3).is_none());
+        assert!(pente.go(9, 9).is_some());
+        assert!(pente.get_marks().is_empty());
+        assert!(pente.go(10, 10).is_some());
+        //黑方第二手距离天元至少三路
+        assert_eq!(pente.get_marks().len(), 5 * 5 - 2);
+        assert!(pente.go(11, 9).is_none());
+        assert!(pente.go(7, 11).is_none());
+        assert!(pente.go(12, 9).is_some());
+        assert!(pente.get_marks().is_empty());
+        assert!(pente.go(9, 10).is_some());
+    }
+}
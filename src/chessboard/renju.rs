@@ -0,0 +1,208 @@
+//! 五子棋类棋盘的禁手判断。
+
+use crate::chessboard::gomoku::{DIRECTIONS, get_cell, line_through};
+
+/// 禁手规则。
+#[derive(Clone, Copy, PartialEq)]
+pub enum ForbiddenRule {
+    /// 连珠：长连、四四、三三
+    Renju,
+    /// 只禁三三，例如 Omok
+    DoubleThree,
+}
+
+/// 判断黑方在 `(x, y)` 落子是否为禁手。
+///
+/// 正好五连优先于一切禁手；连珠规则下长连、四四（包括同一条线上的两个四）、三三均为禁手，
+/// `DoubleThree` 规则下只有三三为禁手。
+/// 只有能走成活四、且成活四的那一点本身不是禁手的三才算活三，需要递归判断。
+/// 判断过程中会临时在棋盘上落子，返回前恢复原状。
+pub(crate) fn is_forbidden(board: &mut [Vec<i32>], x: i32, y: i32, rule: ForbiddenRule) -> bool {
+    if get_cell(board, x, y) != Some(-1) {
+        return false;
+    }
+    board[x as usize][y as usize] = 0;
+    let forbidden = forbidden_after_placed(board, x, y, rule);
+    board[x as usize][y as usize] = -1;
+    forbidden
+}
+
+fn forbidden_after_placed(board: &mut [Vec<i32>], x: i32, y: i32, rule: ForbiddenRule) -> bool {
+    let lengths = DIRECTIONS.map(|(dx, dy)| line_through(board, x, y, dx, dy).len());
+    if lengths.contains(&5) {
+        return false;
+    }
+    if rule == ForbiddenRule::Renju && lengths.iter().any(|&len| len > 5) {
+        return true;
+    }
+    let fours = DIRECTIONS.map(|(dx, dy)| count_fours(board, x, y, dx, dy));
+    if rule == ForbiddenRule::Renju && fours.iter().sum::<usize>() >= 2 {
+        return true;
+    }
+    //已经成四的方向不再算三
+    let threes = DIRECTIONS
+        .iter()
+        .zip(fours)
+        .filter(|&(&(dx, dy), four)| four == 0 && is_three(board, x, y, dx, dy, rule))
+        .count();
+    threes >= 2
+}
+
+/// 黑方在 `(x, y)` 落子后，若在 `(dx, dy)` 方向补一子成五，列出每个四对应的棋子和成五点。
+///
+/// # 返回值
+/// 返回 `(mask, 成五点)` 列表。`mask` 为该四的四颗黑子相对 `(x, y)` 的偏移（-4..=4）组成的位掩码，
+/// 同一个 `mask` 出现两个不同的成五点即为活四。成五必须正好五连，长连不算。
+fn five_points(board: &mut [Vec<i32>], x: i32, y: i32, dx: i32, dy: i32) -> Vec<(u16, (i32, i32))> {
+    let mut points = vec![];
+    for start in -4..=0 {
+        let mut mask = 0u16;
+        let mut empty = None;
+        let mut valid = true;
+        for k in start..start + 5 {
+            let (cx, cy) = (x + dx * k, y + dy * k);
+            match get_cell(board, cx, cy) {
+                Some(0) => mask |= 1 << (k + 4),
+                Some(-1) if empty.is_none() => empty = Some((cx, cy)),
+                _ => {
+                    valid = false;
+                    break;
+                }
+            }
+        }
+        let Some((qx, qy)) = empty else {
+            continue;
+        };
+        if !valid {
+            continue;
+        }
+        board[qx as usize][qy as usize] = 0;
+        let exact = line_through(board, qx, qy, dx, dy).len() == 5;
+        board[qx as usize][qy as usize] = -1;
+        if exact {
+            points.push((mask, (qx, qy)));
+        }
+    }
+    points
+}
+
+fn count_fours(board: &mut [Vec<i32>], x: i32, y: i32, dx: i32, dy: i32) -> usize {
+    let mut masks = five_points(board, x, y, dx, dy)
+        .into_iter()
+        .map(|(mask, _)| mask)
+        .collect::<Vec<u16>>();
+    masks.sort();
+    masks.dedup();
+    masks.len()
+}
+
+fn has_straight_four(board: &mut [Vec<i32>], x: i32, y: i32, dx: i32, dy: i32) -> bool {
+    let points = five_points(board, x, y, dx, dy);
+    points
+        .iter()
+        .any(|(mask, q)| points.iter().any(|(m, p)| m == mask && p != q))
+}
+
+//在 (dx, dy) 方向上能否再补一子走成活四，且补的那一点不是禁手
+fn is_three(board: &mut [Vec<i32>], x: i32, y: i32, dx: i32, dy: i32, rule: ForbiddenRule) -> bool {
+    for k in -3..=3 {
+        let (qx, qy) = (x + dx * k, y + dy * k);
+        if k == 0 || get_cell(board, qx, qy) != Some(-1) {
+            continue;
+        }
+        board[qx as usize][qy as usize] = 0;
+        let straight = has_straight_four(board, x, y, dx, dy);
+        board[qx as usize][qy as usize] = -1;
+        if straight && !is_forbidden(board, qx, qy, rule) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //15 * 15 棋盘，摆上黑子 `black` 和白子 `white`
+    fn board(black: &[(i32, i32)], white: &[(i32, i32)]) -> Vec<Vec<i32>> {
+        let mut board = vec![vec![-1; 15]; 15];
+        for &(x, y) in black {
+            board[x as usize][y as usize] = 0;
+        }
+        for &(x, y) in white {
+            board[x as usize][y as usize] = 1;
+        }
+        board
+    }
+
+    fn forbidden(black: &[(i32, i32)], white: &[(i32, i32)], rule: ForbiddenRule) -> bool {
+        let mut board = board(black, white);
+        let before = board.clone();
+        let forbidden = is_forbidden(&mut board, 7, 7, rule);
+        assert_eq!(board, before, "判断禁手之后棋盘应当恢复原状");
+        forbidden
+    }
+
+    #[test]
+    fn double_three() {
+        let black = [(7, 5), (7, 6), (5, 7), (6, 7)];
+        assert!(forbidden(&black, &[], ForbiddenRule::Renju));
+        assert!(forbidden(&black, &[], ForbiddenRule::DoubleThree));
+        //一端被堵住的三不是活三
+        assert!(!forbidden(&black, &[(8, 7)], ForbiddenRule::Renju));
+    }
+
+    #[test]
+    fn double_four() {
+        let black = [(7, 4), (7, 5), (7, 6), (4, 7), (5, 7), (6, 7)];
+        assert!(forbidden(&black, &[], ForbiddenRule::Renju));
+        assert!(!forbidden(&black, &[], ForbiddenRule::DoubleThree));
+    }
+
+    #[test]
+    fn double_four_on_one_line() {
+        //● _ ● ● ● _ ●，中间一子同时形成两个四
+        let black = [(4, 7), (6, 7), (8, 7), (10, 7)];
+        assert!(forbidden(&black, &[], ForbiddenRule::Renju));
+        assert!(!forbidden(&black, &[], ForbiddenRule::DoubleThree));
+    }
+
+    #[test]
+    fn overline() {
+        let black = [(2, 7), (3, 7), (4, 7), (5, 7), (6, 7), (8, 7)];
+        assert!(forbidden(&black, &[], ForbiddenRule::Renju));
+        assert!(!forbidden(&black, &[], ForbiddenRule::DoubleThree));
+    }
+
+    #[test]
+    fn exact_five_overrides_forbidden_shapes() {
+        let black = [
+            (3, 7),
+            (4, 7),
+            (5, 7),
+            (6, 7),
+            (7, 4),
+            (7, 5),
+            (7, 6),
+            (4, 4),
+            (5, 5),
+            (6, 6),
+        ];
+        assert!(!forbidden(&black, &[], ForbiddenRule::Renju));
+        //白子堵住横线之后只剩两个四
+        let black = &black[1..];
+        assert!(forbidden(black, &[(8, 7)], ForbiddenRule::Renju));
+    }
+
+    #[test]
+    fn three_whose_four_point_is_forbidden_is_not_a_three() {
+        //横线的三只能在 (4, 7) 走成活四，(4, 7) 在竖线上成长连，是禁手
+        let three_three = [(5, 7), (6, 7), (7, 5), (7, 6)];
+        let overline = [(4, 4), (4, 5), (4, 6), (4, 8), (4, 9)];
+        let white = [(9, 7)];
+        assert!(forbidden(&three_three, &white, ForbiddenRule::Renju));
+        let black = [&three_three[..], &overline[..]].concat();
+        assert!(!forbidden(&black, &white, ForbiddenRule::Renju));
+    }
+}
@@ -0,0 +1,570 @@
+//! 围棋：提子、打劫、自杀规则、停一手、贴目以及数子/数目。
+
+use iced::Color;
+
+use crate::chessboard::{
+    chessboard_trait::{ChessboardTrait, GameResult, Mark, Player},
+    get_coord,
+    gomoku::{board_owners, get_cell, piece_value},
+};
+
+/// 打劫规则。
+#[derive(Clone, Copy, PartialEq)]
+pub enum KoRule {
+    /// 不能立即提回，即不能回到对方上一手之前的局面
+    Simple,
+    /// 全局同形禁止，不能回到之前出现过的任何局面
+    Superko,
+}
+
+/// 计分方式。
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scoring {
+    /// 数子：活子加围住的空点
+    Area,
+    /// 数目：围住的空点加提子
+    Territory,
+}
+
+/// 围棋规则。
+#[derive(Clone, Copy)]
+pub struct GoRules {
+    /// 发送给引擎 `kata-set-rules` 的规则名
+    pub name: &'static str,
+    pub ko: KoRule,
+    pub scoring: Scoring,
+    /// 是否允许自杀（多子自杀）
+    pub suicide: bool,
+    pub komi: f64,
+}
+
+impl GoRules {
+    /// 中国规则：数子，全局同形禁止，贴 7.5
+    pub fn chinese() -> Self {
+        GoRules {
+            name: "chinese",
+            ko: KoRule::Superko,
+            scoring: Scoring::Area,
+            suicide: false,
+            komi: 7.5,
+        }
+    }
+
+    /// 日本规则：数目，只禁止立即提回，贴 6.5
+    pub fn japanese() -> Self {
+        GoRules {
+            name: "japanese",
+            ko: KoRule::Simple,
+            scoring: Scoring::Territory,
+            suicide: false,
+            komi: 6.5,
+        }
+    }
+
+    /// Tromp-Taylor 规则：数子，全局同形禁止，允许自杀，贴 7.5
+    pub fn tromp_taylor() -> Self {
+        GoRules {
+            name: "tromp-taylor",
+            ko: KoRule::Superko,
+            scoring: Scoring::Area,
+            suicide: true,
+            komi: 7.5,
+        }
+    }
+}
+
+const NEIGHBORS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+pub struct Weiqi {
+    // -1: empty 0: black 1: white
+    board: Vec<Vec<i32>>,
+    size_x: u32,
+    size_y: u32,
+    current_player: i32,
+    rules: GoRules,
+    komi: f64,
+    //双方各自提走的对方棋子数
+    captures: [u32; 2],
+    //每一手之前的局面，用于判断打劫
+    history: Vec<Vec<Vec<i32>>>,
+    //连续停一手的次数，双方连续停一手后对局结束，进入点死子阶段
+    passes: u32,
+    //终局时标记为死子的棋子
+    dead: Vec<(i32, i32)>,
+}
+
+impl Weiqi {
+    pub fn new(rules: GoRules, width: u32, height: u32) -> Self {
+        Weiqi {
+            board: vec![vec![-1; height as usize]; width as usize],
+            size_x: width,
+            size_y: height,
+            current_player: 0,
+            rules,
+            komi: rules.komi,
+            captures: [0, 0],
+            history: vec![],
+            passes: 0,
+            dead: vec![],
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.passes >= 2
+    }
+
+    //与 (x, y) 相连的同色棋子（或空点）以及它们的气数
+    fn group(board: &[Vec<i32>], x: i32, y: i32) -> (Vec<(i32, i32)>, usize) {
+        let color = board[x as usize][y as usize];
+        let mut stones = vec![(x, y)];
+        let mut liberties = vec![];
+        let mut i = 0;
+        while i < stones.len() {
+            let (cx, cy) = stones[i];
+            for (dx, dy) in NEIGHBORS {
+                let (nx, ny) = (cx + dx, cy + dy);
+                match get_cell(board, nx, ny) {
+                    Some(c) if c == color && !stones.contains(&(nx, ny)) => stones.push((nx, ny)),
+                    Some(-1) if !liberties.contains(&(nx, ny)) => liberties.push((nx, ny)),
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        (stones, liberties.len())
+    }
+
+    //提掉没有气的棋子，返回提子数
+    fn remove_dead(board: &mut [Vec<i32>], x: i32, y: i32) -> u32 {
+        if get_cell(board, x, y).is_none_or(|c| c == -1) {
+            return 0;
+        }
+        let (stones, liberties) = Self::group(board, x, y);
+        if liberties > 0 {
+            return 0;
+        }
+        for &(sx, sy) in &stones {
+            board[sx as usize][sy as usize] = -1;
+        }
+        stones.len() as u32
+    }
+
+    //去掉死子后每个空点的归属：0 黑 1 白 -1 单官
+    fn territory(&self) -> (Vec<Vec<i32>>, Vec<Vec<i32>>) {
+        let mut board = self.board.clone();
+        for &(x, y) in &self.dead {
+            board[x as usize][y as usize] = -1;
+        }
+        let mut owner = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
+        let mut visited = vec![vec![false; self.size_y as usize]; self.size_x as usize];
+        for x in 0..self.size_x as i32 {
+            for y in 0..self.size_y as i32 {
+                if board[x as usize][y as usize] != -1 || visited[x as usize][y as usize] {
+                    continue;
+                }
+                let (region, _) = Self::group(&board, x, y);
+                let mut borders = [false, false];
+                for &(rx, ry) in &region {
+                    visited[rx as usize][ry as usize] = true;
+                    for (dx, dy) in NEIGHBORS {
+                        if let Some(c @ 0..=1) = get_cell(&board, rx + dx, ry + dy) {
+                            borders[c as usize] = true;
+                        }
+                    }
+                }
+                let color = match borders {
+                    [true, false] => 0,
+                    [false, true] => 1,
+                    _ => -1,
+                };
+                for &(rx, ry) in &region {
+                    owner[rx as usize][ry as usize] = color;
+                }
+            }
+        }
+        (board, owner)
+    }
+
+    /// 按规则计算双方得分（白方已含贴目）。
+    pub fn score(&self) -> (f64, f64) {
+        let (board, owner) = self.territory();
+        let mut score = [0.0, 0.0];
+        for c in owner.iter().flatten().filter(|&&c| c != -1) {
+            score[*c as usize] += 1.0;
+        }
+        match self.rules.scoring {
+            Scoring::Area => {
+                for c in board.iter().flatten().filter(|&&c| c != -1) {
+                    score[*c as usize] += 1.0;
+                }
+            }
+            Scoring::Territory => {
+                score[0] += self.captures[0] as f64;
+                score[1] += self.captures[1] as f64;
+                //死子算作对方的提子
+                for &(x, y) in &self.dead {
+                    let c = self.board[x as usize][y as usize];
+                    score[1 - c as usize] += 1.0;
+                }
+            }
+        }
+        (score[0], score[1] + self.komi)
+    }
+
+    fn player_name(&self) -> &'static str {
+        if self.current_player == 0 { "B" } else { "W" }
+    }
+}
+
+impl Default for Weiqi {
+    fn default() -> Self {
+        Self::new(GoRules::chinese(), 19, 19)
+    }
+}
+
+impl ChessboardTrait for Weiqi {
+    fn get_length(&self) -> (u32, u32) {
+        (self.size_x, self.size_y)
+    }
+    fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
+        let black = (Color::BLACK, Color::WHITE);
+        let white = (Color::WHITE, Color::BLACK);
+        self.board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| match cell {
+                        0 => Some(black),
+                        1 => Some(white),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    fn go(&mut self, x: i32, y: i32) -> Option<String> {
+        if self.is_finished() || get_cell(&self.board, x, y)? != -1 {
+            return None;
+        }
+        let before = self.board.clone();
+        let color = self.current_player;
+        self.board[x as usize][y as usize] = color;
+        let mut captured = 0;
+        for (dx, dy) in NEIGHBORS {
+            if get_cell(&self.board, x + dx, y + dy) == Some(1 - color) {
+                captured += Self::remove_dead(&mut self.board, x + dx, y + dy);
+            }
+        }
+        let mut suicided = 0;
+        if Self::group(&self.board, x, y).1 == 0 {
+            if !self.rules.suicide {
+                self.board = before;
+                return None;
+            }
+            suicided = Self::remove_dead(&mut self.board, x, y);
+        }
+        let repeated = match self.rules.ko {
+            KoRule::Simple => self.history.last() == Some(&self.board),
+            KoRule::Superko => self.board == before || self.history.contains(&self.board),
+        };
+        if repeated {
+            self.board = before;
+            return None;
+        }
+        self.history.push(before);
+        self.captures[color as usize] += captured;
+        self.captures[1 - color as usize] += suicided;
+        self.passes = 0;
+        let p = self.player_name();
+        self.current_player = 1 - self.current_player;
+        Some(format!("play {} {}", p, get_coord(x, y, self.size_y)))
+    }
+    fn pass(&mut self) -> Option<String> {
+        if self.is_finished() {
+            return None;
+        }
+        self.history.push(self.board.clone());
+        self.passes += 1;
+        let p = self.player_name();
+        self.current_player = 1 - self.current_player;
+        Some(format!("play {} pass", p))
+    }
+    fn toggle_dead(&mut self, x: i32, y: i32) -> Option<Vec<(u32, u32)>> {
+        if !self.is_finished() || get_cell(&self.board, x, y)? == -1 {
+            return None;
+        }
+        let (stones, _) = Self::group(&self.board, x, y);
+        if self.dead.contains(&(x, y)) {
+            self.dead.retain(|p| !stones.contains(p));
+        } else {
+            self.dead.extend(stones);
+        }
+        Some(
+            self.dead
+                .iter()
+                .map(|&(x, y)| (x as u32, y as u32))
+                .collect(),
+        )
+    }
+    fn set_dead(&mut self, dead: &[(u32, u32)]) {
+        if !self.is_finished() {
+            return;
+        }
+        self.dead = dead
+            .iter()
+            .map(|&(x, y)| (x as i32, y as i32))
+            .filter(|&(x, y)| get_cell(&self.board, x, y).is_some_and(|c| c != -1))
+            .collect();
+    }
+    fn new_board(&mut self) {
+        self.board = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
+        self.current_player = 0;
+        self.captures = [0, 0];
+        self.history.clear();
+        self.passes = 0;
+        self.dead.clear();
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        if get_cell(&self.board, x, y).is_none() {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        //摆出的局面重新开始判断打劫和终局
+        self.history.clear();
+        self.passes = 0;
+        self.dead.clear();
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
+    }
+    fn get_player(&self) -> Player {
+        if self.current_player == 1 {
+            Player::White
+        } else {
+            Player::Black
+        }
+    }
+    fn get_result(&self) -> Option<GameResult> {
+        if !self.is_finished() {
+            return None;
+        }
+        let (black, white) = self.score();
+        let winner = if black > white {
+            Some(Player::Black)
+        } else if white > black {
+            Some(Player::White)
+        } else {
+            None
+        };
+        Some(GameResult {
+            winner,
+            line: vec![],
+        })
+    }
+    fn get_marks(&self) -> Vec<(u32, u32, Mark)> {
+        if !self.is_finished() {
+            return vec![];
+        }
+        let mut marks = self
+            .dead
+            .iter()
+            .map(|&(x, y)| (x as u32, y as u32, Mark::Cross(Color::from_rgb8(241, 9, 9))))
+            .collect::<Vec<_>>();
+        let (_, owner) = self.territory();
+        for (x, row) in owner.iter().enumerate() {
+            for (y, &c) in row.iter().enumerate() {
+                //死子上已经画了叉号
+                if self.board[x][y] != -1 {
+                    continue;
+                }
+                let color = match c {
+                    0 => Color::BLACK,
+                    1 => Color::WHITE,
+                    _ => continue,
+                };
+                marks.push((x as u32, y as u32, Mark::Dot(color)));
+            }
+        }
+        marks
+    }
+    fn get_rules(&self) -> Option<String> {
+        Some(self.rules.name.to_string())
+    }
+    fn get_komi(&self) -> Option<f64> {
+        Some(self.komi)
+    }
+    fn set_komi(&mut self, komi: f64) {
+        self.komi = komi;
+    }
+    fn get_info(&self) -> Option<String> {
+        if self.is_finished() {
+            let (black, white) = self.score();
+            Some(format!(
+                "黑 {}，白 {}（含贴目 {}），点击棋子标记死子",
+                black, white, self.komi
+            ))
+        } else {
+            Some(format!("贴目 {}", self.komi))
+        }
+    }
+    fn get_captures(&self) -> Option<(u32, u32)> {
+        Some((self.captures[0], self.captures[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //`size * size` 的棋盘上摆出黑子 `black` 和白子 `white`，轮到黑方
+    fn position(rules: GoRules, size: u32, black: &[(i32, i32)], white: &[(i32, i32)]) -> Weiqi {
+        let mut board = Weiqi::new(rules, size, size);
+        for &(x, y) in black {
+            board.set_piece(x, y, Some(Player::Black));
+        }
+        for &(x, y) in white {
+            board.set_piece(x, y, Some(Player::White));
+        }
+        board
+    }
+
+    #[test]
+    fn capture() {
+        for rules in [
+            GoRules::chinese(),
+            GoRules::japanese(),
+            GoRules::tromp_taylor(),
+        ] {
+            let mut board = position(rules, 5, &[(1, 0)], &[(0, 0)]);
+            assert!(board.go(0, 1).is_some());
+            assert_eq!(board.board[0][0], -1);
+            assert_eq!(board.get_captures(), Some((1, 0)));
+        }
+    }
+
+    #[test]
+    fn suicide() {
+        //白方在 (1, 1) 落子后整块四子没有气
+        let black = [(2, 0), (2, 1), (1, 2), (0, 2)];
+        let white = [(0, 0), (1, 0), (0, 1)];
+        for rules in [GoRules::chinese(), GoRules::japanese()] {
+            let mut board = position(rules, 5, &black, &white);
+            board.set_player(Player::White);
+            assert_eq!(board.go(1, 1), None);
+            assert_eq!(board.get_player(), Player::White);
+        }
+        let mut board = position(GoRules::tromp_taylor(), 5, &black, &white);
+        board.set_player(Player::White);
+        assert!(board.go(1, 1).is_some());
+        assert!((0..2).all(|x| (0..2).all(|y| board.board[x][y] == -1)));
+        assert_eq!(board.get_captures(), Some((4, 0)));
+        //单子自杀之后局面不变，全局同形禁止
+        let mut board = position(GoRules::tromp_taylor(), 5, &[(1, 0), (0, 1)], &[]);
+        board.set_player(Player::White);
+        assert_eq!(board.go(0, 0), None);
+    }
+
+    //. ● ○ .
+    //● ○ . ○
+    //. ● ○ .
+    fn ko(rules: GoRules) -> Weiqi {
+        position(
+            rules,
+            7,
+            &[(1, 0), (0, 1), (1, 2)],
+            &[(2, 0), (1, 1), (3, 1), (2, 2)],
+        )
+    }
+
+    #[test]
+    fn simple_ko() {
+        for rules in [
+            GoRules::chinese(),
+            GoRules::japanese(),
+            GoRules::tromp_taylor(),
+        ] {
+            let mut board = ko(rules);
+            assert!(board.go(2, 1).is_some());
+            //不能立即提回
+            assert_eq!(board.go(1, 1), None);
+            assert!(board.go(5, 5).is_some());
+            assert!(board.go(5, 4).is_some());
+            //中间隔了两手，局面已经不同，可以提回
+            assert!(board.go(1, 1).is_some());
+        }
+    }
+
+    #[test]
+    fn superko() {
+        for (rules, allowed) in [
+            (GoRules::japanese(), true),
+            (GoRules::chinese(), false),
+            (GoRules::tromp_taylor(), false),
+        ] {
+            let mut board = ko(rules);
+            let start = board.board.clone();
+            assert!(board.go(2, 1).is_some());
+            //在历史中补一个中间局面，提回之后的局面不是上一手之前的局面，而是更早出现过的局面
+            let mut between = board.board.clone();
+            between[5][5] = 0;
+            board.history.push(between);
+            assert_eq!(board.go(1, 1).is_some(), allowed);
+            if allowed {
+                assert_eq!(board.board, start);
+            }
+        }
+    }
+
+    //黑方围住左边两列，白方围住右边一列，白方在黑空中有一个死子
+    fn finished(rules: GoRules) -> Weiqi {
+        let black = (0..5).map(|y| (2, y)).collect::<Vec<_>>();
+        let white = (0..5).map(|y| (3, y)).chain([(0, 0)]).collect::<Vec<_>>();
+        let mut board = position(rules, 5, &black, &white);
+        board.set_komi(0.5);
+        assert_eq!(board.toggle_dead(0, 0), None);
+        assert!(board.pass().is_some());
+        assert!(board.pass().is_some());
+        board
+    }
+
+    #[test]
+    fn area_scoring() {
+        let mut board = finished(GoRules::chinese());
+        //死子没有标记时，黑空与白子相邻，不算任何一方的地
+        assert_eq!(board.score(), (5.0, 11.5));
+        assert_eq!(board.toggle_dead(0, 0), Some(vec![(0, 0)]));
+        assert_eq!(board.score(), (15.0, 10.5));
+        assert_eq!(board.get_result().unwrap().winner, Some(Player::Black));
+        //再点一次取消死子
+        assert_eq!(board.toggle_dead(0, 0), Some(vec![]));
+        assert_eq!(board.score(), (5.0, 11.5));
+    }
+
+    #[test]
+    fn territory_scoring() {
+        let mut board = finished(GoRules::japanese());
+        board.captures = [2, 1];
+        assert_eq!(board.score(), (2.0, 6.5));
+        board.toggle_dead(0, 0);
+        //死子算作黑方的提子
+        assert_eq!(board.score(), (13.0, 6.5));
+    }
+
+    #[test]
+    fn dead_stones_survive_replay() {
+        let mut board = finished(GoRules::chinese());
+        let dead = board.toggle_dead(0, 0).unwrap();
+        board.dead.clear();
+        board.set_dead(&dead);
+        assert_eq!(board.score(), (15.0, 10.5));
+        //对局未结束时忽略
+        board.passes = 0;
+        board.dead.clear();
+        board.set_dead(&dead);
+        assert!(board.dead.is_empty());
+    }
+}
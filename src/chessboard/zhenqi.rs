@@ -1,21 +1,86 @@
+//! 震棋：落子后把周围八个方向紧挨着的棋子向外推一格，
+//! 推出棋盘的棋子被移除。推完之后连成五子的一方获胜，双方同时连成五子时落子方获胜，
+//! 棋盘下满仍无人连五为和棋。
+//!
+//! 默认为 8 * 8 的棋盘，`zhenqi12x` 等 12 * 12 的引擎在“棋盘大小...”中选择 12 * 12，规则不变。
+
 use iced::Color;
 
-use crate::chessboard::chessboard_trait::ChessboardTrait;
+use crate::chessboard::{
+    chessboard_trait::{ChessboardTrait, GameResult, Player},
+    get_coord,
+    gomoku::{DIRECTIONS, board_owners, get_cell, piece_value},
+};
+
+//连成几子获胜，与棋盘大小无关
+const GOAL: usize = 5;
 
 pub struct Zhenqi {
     board: Vec<Vec<i32>>,
     current_player: i32,
     size_x: u32,
     size_y: u32,
+    result: Option<GameResult>,
 }
 impl Zhenqi {
-    pub fn new() -> Self {
+    pub fn new(width: u32, height: u32) -> Self {
         Zhenqi {
-            board: vec![vec![-1; 8]; 8],
+            board: vec![vec![-1; height as usize]; width as usize],
             current_player: 0,
-            size_x: 8,
-            size_y: 8,
+            size_x: width,
+            size_y: height,
+            result: None,
+        }
+    }
+
+    //推子之后棋盘上 color 连成五子及以上的一条线，推子会移动很多棋子，所以需要检查整个棋盘
+    fn find_line(&self, color: i32) -> Option<Vec<(u32, u32)>> {
+        for x in 0..self.size_x as i32 {
+            for y in 0..self.size_y as i32 {
+                if self.board[x as usize][y as usize] != color {
+                    continue;
+                }
+                for (dx, dy) in DIRECTIONS {
+                    //只从一条线的起点开始数
+                    if get_cell(&self.board, x - dx, y - dy) == Some(color) {
+                        continue;
+                    }
+                    let mut line = vec![];
+                    let (mut cx, mut cy) = (x, y);
+                    while get_cell(&self.board, cx, cy) == Some(color) {
+                        line.push((cx as u32, cy as u32));
+                        cx += dx;
+                        cy += dy;
+                    }
+                    if line.len() >= GOAL {
+                        return Some(line);
+                    }
+                }
+            }
         }
+        None
+    }
+
+    fn update_result(&mut self) {
+        let mover = self.current_player;
+        let winner = [mover, 1 - mover]
+            .into_iter()
+            .find_map(|color| Some((color, self.find_line(color)?)));
+        self.result = match winner {
+            Some((color, line)) => Some(GameResult {
+                winner: Some(if color == 0 {
+                    Player::Black
+                } else {
+                    Player::White
+                }),
+                line,
+            }),
+            None if self.board.iter().flatten().all(|&cell| cell != -1) => Some(GameResult {
+                winner: None,
+                line: vec![],
+            }),
+            None => None,
+        };
     }
     fn get_state(&self, x: i32, y: i32) -> PieceState {
         if x < 0 || x > self.size_x as i32 - 1 || y < 0 || y > self.size_y as i32 - 1 {
@@ -30,11 +95,17 @@ impl Zhenqi {
     }
 }
 
+impl Default for Zhenqi {
+    fn default() -> Self {
+        Self::new(8, 8)
+    }
+}
+
 impl ChessboardTrait for Zhenqi {
     fn get_length(&self) -> (u32, u32) {
         (self.size_x, self.size_y)
     }
-    fn get_pieces(&self) -> Vec<Vec<Option<(iced::Color, iced::Color)>>> {
+    fn get_pieces(&self) -> Vec<Vec<Option<(Color, Color)>>> {
         let black = (Color::BLACK, Color::WHITE);
         let white = (Color::WHITE, Color::BLACK);
         self.board
@@ -55,7 +126,7 @@ impl ChessboardTrait for Zhenqi {
         if x < 0 || x > self.size_x as i32 - 1 || y < 0 || y > self.size_y as i32 - 1 {
             return None;
         }
-        if self.board[x as usize][y as usize] != -1 {
+        if self.result.is_some() || self.board[x as usize][y as usize] != -1 {
             return None;
         }
         self.board[x as usize][y as usize] = self.current_player;
@@ -84,30 +155,40 @@ impl ChessboardTrait for Zhenqi {
                 }
             }
         }
+        self.update_result();
         let p = if self.current_player == 0 { "B" } else { "W" };
         self.current_player = 1 - self.current_player;
-        let mut p_x = x;
-        if p_x >= 'I' as i32 - 'A' as i32 {
-            p_x = p_x + 1;
-        }
-        Some(format!(
-            "play {} {}{}",
-            p,
-            ('A' as i32 + p_x) as u8 as char,
-            self.size_y as i32 - y
-        ))
+        Some(format!("play {} {}", p, get_coord(x, y, self.size_y)))
     }
     fn new_board(&mut self) {
-        self.board = vec![vec![-1; 15]; 15];
+        self.board = vec![vec![-1; self.size_y as usize]; self.size_x as usize];
         self.current_player = 0;
+        self.result = None;
+    }
+    fn get_owners(&self) -> Vec<Vec<Option<Player>>> {
+        board_owners(&self.board)
+    }
+    fn set_piece(&mut self, x: i32, y: i32, piece: Option<Player>) -> bool {
+        if get_cell(&self.board, x, y).is_none() {
+            return false;
+        }
+        self.board[x as usize][y as usize] = piece_value(piece);
+        self.result = None;
+        true
+    }
+    fn set_player(&mut self, player: Player) {
+        self.current_player = piece_value(Some(player));
     }
-    fn get_player(&self) -> super::chessboard_trait::Player {
+    fn get_player(&self) -> Player {
         if self.current_player == 1 {
-            super::chessboard_trait::Player::White
+            Player::White
         } else {
-            super::chessboard_trait::Player::Black
+            Player::Black
         }
     }
+    fn get_result(&self) -> Option<GameResult> {
+        self.result.clone()
+    }
 }
 
 #[derive(PartialEq)]
@@ -116,3 +197,104 @@ enum PieceState {
     Empty,
     Outside,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(board: &mut Zhenqi, stones: &[(i32, i32)], player: Player) {
+        for &(x, y) in stones {
+            assert!(board.set_piece(x, y, Some(player)));
+        }
+    }
+
+    #[test]
+    fn push_and_eject() {
+        let mut board = Zhenqi::new(8, 8);
+        place(&mut board, &[(4, 4), (0, 2), (2, 3), (1, 3)], Player::White);
+        assert!(board.go(3, 3).is_some());
+        //向外推一格
+        assert_eq!(board.board[4][4], -1);
+        assert_eq!(board.board[5][5], 1);
+        //后面有棋子时推不动
+        assert_eq!(board.board[2][3], 1);
+        assert_eq!(board.board[1][3], 1);
+        assert!(board.go(1, 2).is_some());
+        //推出棋盘的棋子被移除，落子方自己的棋子也会被推开
+        assert_eq!(board.board[0][2], -1);
+        assert_eq!(board.board[3][4], 1);
+        assert_eq!(board.board[1][4], 1);
+        assert_eq!(board.board[3][3], 0);
+        assert_eq!(board.get_player(), Player::Black);
+    }
+
+    #[test]
+    fn five_in_a_row_wins() {
+        let mut board = Zhenqi::new(8, 8);
+        place(&mut board, &[(1, 0), (2, 0), (3, 0), (4, 0)], Player::Black);
+        assert!(board.go(5, 0).is_some());
+        let result = board.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::Black));
+        assert_eq!(result.line.len(), GOAL);
+        assert!(board.go(7, 7).is_none());
+        //四子不算
+        board.new_board();
+        assert_eq!(board.get_length(), (8, 8));
+        place(&mut board, &[(1, 0), (2, 0), (3, 0)], Player::Black);
+        board.go(4, 0);
+        assert!(board.get_result().is_none());
+    }
+
+    #[test]
+    fn mover_wins_when_both_make_five() {
+        let mut board = Zhenqi::new(12, 12);
+        place(&mut board, &[(5, 4), (6, 4), (7, 4), (8, 4)], Player::Black);
+        place(
+            &mut board,
+            &[(0, 2), (1, 2), (2, 2), (3, 2), (4, 3)],
+            Player::White,
+        );
+        //白子 (4, 3) 被推到 (4, 2)，白方同时连成五子
+        assert!(board.go(4, 4).is_some());
+        assert!(board.find_line(1).is_some());
+        let result = board.get_result().unwrap();
+        assert_eq!(result.winner, Some(Player::Black));
+        assert!(result.line.contains(&(4, 4)));
+    }
+
+    #[test]
+    fn pushed_stones_can_complete_the_opponent_five() {
+        let mut board = Zhenqi::new(12, 12);
+        place(
+            &mut board,
+            &[(0, 2), (1, 2), (2, 2), (3, 2), (4, 3)],
+            Player::White,
+        );
+        assert!(board.go(4, 5).is_some());
+        assert!(board.get_result().is_none());
+        board.set_player(Player::Black);
+        assert!(board.go(4, 4).is_some());
+        assert_eq!(board.get_result().unwrap().winner, Some(Player::White));
+    }
+
+    #[test]
+    fn full_board_is_a_draw() {
+        let mut board = Zhenqi::new(5, 5);
+        for x in 0..5 {
+            for y in 0..5 {
+                if (x, y) != (0, 0) {
+                    let player = if (x + 2 * y) % 4 < 2 {
+                        Player::Black
+                    } else {
+                        Player::White
+                    };
+                    place(&mut board, &[(x, y)], player);
+                }
+            }
+        }
+        //下在角上，周围的棋子都推不动
+        assert!(board.go(0, 0).is_some());
+        let result = board.get_result().unwrap();
+        assert_eq!(result.winner, None);
+    }
+}
@@ -28,6 +28,33 @@ impl Analyzes {
         }
         Self { datas }
     }
+
+    /// 解析 Leela Zero 的 `lz-analyze` 输出，例如
+    /// `info move D4 visits 836 winrate 4811 prior 1873 lcb 4762 order 0 pv D4 Q16`。
+    ///
+    /// `winrate`、`prior`、`lcb` 为 0～10000，换算为与 `kata-analyze` 相同的 0～1。
+    ///
+    /// ```
+    /// use rboard::engine::analyze::Analyzes;
+    ///
+    /// let s = "info move D4 visits 836 winrate 4811 prior 1873 lcb 4762 order 0 pv D4 Q16 \
+    ///          info move Q4 visits 12 winrate 4500 prior 900 lcb 4000 order 1 pv Q4";
+    /// let analyzes = Analyzes::from_lz_string(s);
+    /// assert_eq!(analyzes.datas.len(), 2);
+    /// assert_eq!(analyzes.datas[0].move_, "D4");
+    /// assert!((analyzes.datas[0].winrate - 0.4811).abs() < 1e-9);
+    /// assert_eq!(analyzes.datas[0].pv, vec!["D4", "Q16"]);
+    /// assert_eq!(analyzes.datas[1].order, 1);
+    /// ```
+    pub fn from_lz_string(str: &str) -> Self {
+        let mut analyzes = Self::from_string(&str.to_string());
+        for analyze in &mut analyzes.datas {
+            analyze.winrate /= 10000.0;
+            analyze.prior /= 10000.0;
+            analyze.lcb /= 10000.0;
+        }
+        analyzes
+    }
 }
 
 #[derive(Default, Clone, Debug)]
@@ -0,0 +1,127 @@
+use crate::{chessboard::chessboard_trait::Player, engine::analyze::Analyzes};
+
+/// 引擎支持的命令，由 `list_commands` 的回复得到。
+///
+/// ```
+/// use rboard::engine::capabilities::{AnalyzeCommand, Capabilities};
+///
+/// let caps = Capabilities::from_list_commands("name\ngenmove\nundo\nlz-analyze\nkata-analyze");
+/// assert!(caps.genmove && caps.undo && !caps.set_position);
+/// assert_eq!(caps.analyze_command(), Some(AnalyzeCommand::Kata));
+///
+/// let caps = Capabilities::from_list_commands("genmove\nlz-analyze\nlz-genmove_analyze");
+/// assert_eq!(caps.analyze_command(), Some(AnalyzeCommand::Lz));
+/// assert_eq!(caps.genmove_analyze_command(), Some(AnalyzeCommand::Lz));
+/// assert!(caps.supports_size((15, 15)) && !caps.supports_size((15, 13)));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub genmove: bool,
+    pub undo: bool,
+    pub komi: bool,
+    pub set_position: bool,
+    pub rectangular_boardsize: bool,
+    pub kata_analyze: bool,
+    pub lz_analyze: bool,
+    pub kata_genmove_analyze: bool,
+    pub lz_genmove_analyze: bool,
+    pub kata_set_rules: bool,
+    pub kata_get_rules: bool,
+}
+
+impl Capabilities {
+    /// 解析 `list_commands` 的回复，每行一个命令。
+    pub fn from_list_commands(body: &str) -> Self {
+        let mut caps = Capabilities::default();
+        for command in body.lines() {
+            match command.trim() {
+                "genmove" => caps.genmove = true,
+                "undo" => caps.undo = true,
+                "komi" => caps.komi = true,
+                "set_position" => caps.set_position = true,
+                "rectangular_boardsize" => caps.rectangular_boardsize = true,
+                "kata-analyze" => caps.kata_analyze = true,
+                "lz-analyze" => caps.lz_analyze = true,
+                "kata-genmove_analyze" => caps.kata_genmove_analyze = true,
+                "lz-genmove_analyze" => caps.lz_genmove_analyze = true,
+                "kata-set-rules" => caps.kata_set_rules = true,
+                "kata-get-rules" => caps.kata_get_rules = true,
+                _ => {}
+            }
+        }
+        caps
+    }
+
+    /// 分析使用的命令，`kata-analyze` 带有胜率以外的信息，优先使用。
+    pub fn analyze_command(&self) -> Option<AnalyzeCommand> {
+        if self.kata_analyze {
+            Some(AnalyzeCommand::Kata)
+        } else if self.lz_analyze {
+            Some(AnalyzeCommand::Lz)
+        } else {
+            None
+        }
+    }
+
+    /// 能否人机对弈。
+    pub fn can_genmove(&self) -> bool {
+        self.genmove || self.genmove_analyze_command().is_some()
+    }
+
+    /// 人机对弈时代替 `genmove` 的命令，引擎思考时也输出分析信息。
+    pub fn genmove_analyze_command(&self) -> Option<AnalyzeCommand> {
+        if self.kata_genmove_analyze {
+            Some(AnalyzeCommand::Kata)
+        } else if self.lz_genmove_analyze {
+            Some(AnalyzeCommand::Lz)
+        } else {
+            None
+        }
+    }
+
+    /// 引擎是否能设置这个大小的棋盘，长方形棋盘需要 `rectangular_boardsize`。
+    pub fn supports_size(&self, (x, y): (u32, u32)) -> bool {
+        x == y || self.rectangular_boardsize
+    }
+}
+
+/// 分析命令。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnalyzeCommand {
+    Kata,
+    Lz,
+}
+
+impl AnalyzeCommand {
+    /// 分析 `player` 的下一手，每 `interval` 厘秒输出一次 `info`。
+    pub fn command(&self, player: Player, interval: u32) -> String {
+        match self {
+            AnalyzeCommand::Kata => {
+                format!("kata-analyze {} {} pvVisits true", player.gtp(), interval)
+            }
+            AnalyzeCommand::Lz => format!("lz-analyze {} {}", player.gtp(), interval),
+        }
+    }
+
+    /// 替 `player` 落子并输出分析信息，回复为 `play G3`、`play pass` 或 `play resign`。
+    pub fn genmove_command(&self, player: Player, interval: u32) -> String {
+        match self {
+            AnalyzeCommand::Kata => {
+                format!(
+                    "kata-genmove_analyze {} {} pvVisits true",
+                    player.gtp(),
+                    interval
+                )
+            }
+            AnalyzeCommand::Lz => format!("lz-genmove_analyze {} {}", player.gtp(), interval),
+        }
+    }
+
+    /// 按这个命令的格式解析 `info` 输出。
+    pub fn parse(&self, output: &str) -> Analyzes {
+        match self {
+            AnalyzeCommand::Kata => Analyzes::from_string(&output.to_string()),
+            AnalyzeCommand::Lz => Analyzes::from_lz_string(output),
+        }
+    }
+}
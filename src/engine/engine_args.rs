@@ -2,11 +2,63 @@ use std::path::Path;
 
 use json::JsonValue;
 
+/// 引擎使用的协议。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    #[default]
+    Gtp,
+    /// Gomocup 的 Piskvork 协议，例如 Rapfi、Embryo
+    Piskvork,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 2] = [Protocol::Gtp, Protocol::Piskvork];
+
+    /// 按文件名猜测协议，Gomocup 的引擎按惯例命名为 `pbrain-*`。
+    ///
+    /// ```
+    /// use rboard::engine::engine_args::Protocol;
+    ///
+    /// assert_eq!(Protocol::detect("C:\\rapfi\\pbrain-rapfi.exe"), Protocol::Piskvork);
+    /// assert_eq!(Protocol::detect("katago.exe"), Protocol::Gtp);
+    /// ```
+    pub fn detect(path: &str) -> Self {
+        //engines.json 可能来自另一个系统，两种分隔符都要处理
+        let name = path
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if name.starts_with("pbrain-") {
+            Protocol::Piskvork
+        } else {
+            Protocol::Gtp
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            Protocol::Gtp => "gtp",
+            Protocol::Piskvork => "piskvork",
+        }
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Gtp => write!(f, "GTP"),
+            Protocol::Piskvork => write!(f, "Piskvork"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EngineArgs {
     pub path: String,
     pub args: String,
     pub name: String,
+    pub protocol: Protocol,
 }
 
 impl EngineArgs {
@@ -17,6 +69,7 @@ impl EngineArgs {
             .and_then(|s| s.to_str())
             .unwrap_or("engine");
         EngineArgs {
+            protocol: Protocol::detect(&path),
             path,
             args: String::new(),
             name: name.to_string(),
@@ -26,13 +79,27 @@ impl EngineArgs {
         json::object! {
             path: self.path.clone(),
             args: self.args.clone(),
-            name: self.name.clone()
+            name: self.name.clone(),
+            protocol: self.protocol.key()
         }
     }
     pub fn from_json(json: &JsonValue) -> Self {
         let path = json["path"].as_str().unwrap().to_string();
         let args = json["args"].as_str().unwrap().to_string();
         let name = json["name"].as_str().unwrap().to_string();
-        EngineArgs { path, args, name }
+        //旧的 engines.json 没有 protocol，按文件名猜测
+        let protocol = match json["protocol"].as_str() {
+            Some(key) => Protocol::ALL
+                .into_iter()
+                .find(|p| p.key() == key)
+                .unwrap_or_default(),
+            None => Protocol::detect(&path),
+        };
+        EngineArgs {
+            path,
+            args,
+            name,
+            protocol,
+        }
     }
 }
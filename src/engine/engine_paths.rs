@@ -1,8 +1,12 @@
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use json::JsonValue;
 
-use crate::engine::engine_args::EngineArgs;
+use crate::engine::engine_args::{EngineArgs, Protocol};
 
 pub struct EnginePaths {
     pub paths: Vec<EngineArgs>,
@@ -21,7 +25,10 @@ impl EnginePaths {
         Some(current_path.join("engines.json"))
     }
     fn read_to_file() -> Option<Self> {
-        let path = Self::get_current_path()?;
+        Self::load(&Self::get_current_path()?)
+    }
+    /// 从指定的 engines.json 读取引擎列表，例如命令行比赛。
+    pub fn load(path: &Path) -> Option<Self> {
         let s = std::fs::read_to_string(path).ok()?;
         let json = json::parse(s.as_str()).ok()?;
         let path = json["paths"].clone();
@@ -78,6 +85,14 @@ impl EnginePaths {
             Err("index out of bounds".to_string())
         }
     }
+    pub fn change_protocol(&mut self, index: usize, protocol: Protocol) -> Result<(), String> {
+        if index < self.paths.len() {
+            self.paths[index].protocol = protocol;
+            self.save()
+        } else {
+            Err("index out of bounds".to_string())
+        }
+    }
     pub fn delete(&mut self, index: usize) -> Result<(), String> {
         if index < self.paths.len() {
             self.paths.remove(index);
@@ -1,24 +1,183 @@
 use std::{
+    collections::VecDeque,
+    fmt,
     io::{BufRead, BufReader, Write},
-    sync::{Arc, Mutex},
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    task::{Context, Poll},
     thread,
 };
 
-use iced::futures::{SinkExt, executor::block_on};
+use iced::futures::{
+    SinkExt,
+    channel::{mpsc::Sender, oneshot},
+    executor::block_on,
+};
 use subprocess::{Exec, Popen, PopenError, Redirection};
 
+use crate::engine::Engine;
+
+/// 引擎的输出，标准输出按 GTP 的格式拆成回复和分析信息。
+#[derive(Clone, Debug)]
+pub enum EngineOutput {
+    /// 一条命令的完整回复。
+    Response(Response),
+    /// 分析命令持续输出的 `info` 行。
+    Info(String),
+    /// 标准错误，以及不属于任何回复的标准输出。
+    Log(String),
+}
+
+/// 一条命令的回复：`=` 或 `?` 开头，到空行结束。
+#[derive(Clone, Debug)]
+pub struct Response {
+    /// 命令的编号，与 `ResponseHandle::id` 相同。
+    pub id: u32,
+    /// 发送的命令，不含编号。
+    pub command: String,
+    /// `=` 为成功，`?` 为失败。
+    pub success: bool,
+    /// 回复的内容，多行回复用 `\n` 连接。
+    pub body: String,
+}
+
+impl Response {
+    /// 命令名，即命令的第一个词。
+    pub fn name(&self) -> &str {
+        self.command.split_whitespace().next().unwrap_or("")
+    }
+
+    pub fn result(self) -> Result<String, String> {
+        if self.success {
+            Ok(self.body)
+        } else {
+            Err(format!("{}: {}", self.command, self.body))
+        }
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.success { '=' } else { '?' };
+        write!(f, "{}{} {}", sign, self.id, self.body)
+    }
+}
+
+/// 等待一条命令的回复。引擎退出时得到 `Err`。
+///
+/// 不需要回复时可以直接丢弃，回复仍然会出现在输出流中。
+pub struct ResponseHandle {
+    id: u32,
+    rx: oneshot::Receiver<Response>,
+}
+
+impl ResponseHandle {
+    pub(crate) fn new(id: u32, rx: oneshot::Receiver<Response>) -> Self {
+        ResponseHandle { id, rx }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// 阻塞等待回复，`?` 回复也作为错误返回。
+    pub fn wait(self) -> Result<String, String> {
+        block_on(self)?.result()
+    }
+}
+
+impl Future for ResponseHandle {
+    type Output = Result<Response, String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx)
+            .poll(cx)
+            .map(|r| r.map_err(|_| "引擎已退出".to_string()))
+    }
+}
+
+//已经发送、还没有收到回复的命令，GTP 按发送的顺序回复
+struct Pending {
+    id: u32,
+    command: String,
+    tx: oneshot::Sender<Response>,
+}
+
+#[derive(Default)]
+struct PendingQueue {
+    //引擎的标准输出已经关闭，不会再有回复
+    closed: bool,
+    commands: VecDeque<Pending>,
+}
+
+//把标准输出按行拆成回复和分析信息
+#[derive(Default)]
+struct StdoutParser {
+    //正在读取的回复，以及等待它的命令
+    current: Option<(Response, oneshot::Sender<Response>)>,
+}
+
+impl StdoutParser {
+    /// 处理一行输出，回复读完时交给等待它的命令。
+    ///
+    /// # 返回值
+    /// 需要发送到输出流的内容，回复还没有读完时返回 `None`。
+    fn line(&mut self, output: &str, pending: &Mutex<PendingQueue>) -> Option<EngineOutput> {
+        let output = output.trim_end();
+        //回复以空行结束
+        if output.is_empty() {
+            let (response, tx) = self.current.take()?;
+            let _ = tx.send(response.clone());
+            return Some(EngineOutput::Response(response));
+        }
+        if output.starts_with("info ") {
+            return Some(EngineOutput::Info(output.to_string()));
+        }
+        if let Some((response, _)) = &mut self.current {
+            if !response.body.is_empty() {
+                response.body.push('\n');
+            }
+            response.body.push_str(output);
+            return None;
+        }
+        let Some((success, id, body)) = parse_response_line(output) else {
+            return Some(EngineOutput::Log(output.to_string()));
+        };
+        let Some(command) = pending.lock().unwrap().commands.pop_front() else {
+            edebug(format!("没有命令等待回复: {}", output));
+            return Some(EngineOutput::Log(output.to_string()));
+        };
+        if id.is_some_and(|id| id != command.id) {
+            edebug(format!("回复编号不匹配: {} {}", command.id, output));
+        }
+        let response = Response {
+            id: command.id,
+            command: command.command,
+            success,
+            body: body.to_string(),
+        };
+        self.current = Some((response, command.tx));
+        None
+    }
+}
+
 pub struct GTP {
     cmd_tx: std::sync::mpsc::Sender<String>,
     child: Popen,
     cmd_handler: Option<thread::JoinHandle<()>>,
     output_handler: Option<thread::JoinHandle<()>>,
+    pending: Arc<Mutex<PendingQueue>>,
+    next_id: AtomicU32,
 }
 
 impl GTP {
     pub fn start(
         engine_path: &str,
         engine_args: &str,
-        data_tx: Arc<Mutex<iced::futures::channel::mpsc::Sender<String>>>,
+        data_tx: Arc<Mutex<Sender<EngineOutput>>>,
     ) -> Result<Self, String> {
         let (cmd_tx, cmd_rx) = std::sync::mpsc::channel::<String>();
 
@@ -59,6 +218,8 @@ impl GTP {
             eprintln!("命令线程已退出");
         });
 
+        let pending = Arc::new(Mutex::new(PendingQueue::default()));
+        let pending_out = Arc::clone(&pending);
         let data_tx_clone = Arc::clone(&data_tx);
         // 启动输出读取线程
         let output_handler = thread::spawn(move || {
@@ -66,16 +227,27 @@ impl GTP {
             let data_tx_err = Arc::clone(&data_tx_clone);
             // 使用两个线程分别读取 stdout 和 stderr
             let stdout_thread = thread::spawn(move || {
+                let send = |output| {
+                    let _ = block_on(data_tx_out.lock().unwrap().send(output));
+                };
+                let mut parser = StdoutParser::default();
                 let reader = BufReader::new(stdout);
                 for line in reader.lines() {
-                    match line {
-                        Ok(output) => {
-                            let _ = block_on(data_tx_out.lock().unwrap().send(output));
+                    let output = match line {
+                        Ok(output) => output,
+                        Err(e) => {
+                            edebug(format!("读取输出错误: {}", e));
+                            continue;
                         }
-
-                        Err(e) => edebug(format!("读取输出错误: {}", e)),
+                    };
+                    if let Some(output) = parser.line(&output, &pending_out) {
+                        send(output);
                     }
                 }
+                //引擎退出，等待中的命令不会再有回复
+                let mut pending = pending_out.lock().unwrap();
+                pending.closed = true;
+                pending.commands.clear();
             });
 
             let stderr_thread = thread::spawn(move || {
@@ -83,7 +255,8 @@ impl GTP {
                 for line in reader.lines() {
                     match line {
                         Ok(err) => {
-                            let _ = block_on(data_tx_err.lock().unwrap().send(err));
+                            let _ =
+                                block_on(data_tx_err.lock().unwrap().send(EngineOutput::Log(err)));
                         }
                         Err(e) => edebug(format!("读取错误输出错误: {}", e)),
                     }
@@ -100,20 +273,37 @@ impl GTP {
             child,
             cmd_handler: Some(cmd_handler),
             output_handler: Some(output_handler),
+            pending,
+            next_id: AtomicU32::new(1),
         })
     }
+}
 
-    pub fn send_command(&self, command: String) -> Result<(), String> {
+impl Engine for GTP {
+    /// 发送一条命令，命令前加上编号，返回等待回复的句柄。
+    ///
+    /// 回复同时会作为 `EngineOutput::Response` 出现在输出流中。
+    /// 每条命令只对应一个编号和一条回复，包含换行的命令返回 `Err`，需要分成多条发送。
+    fn send_command(&self, command: String) -> Result<ResponseHandle, String> {
+        let command = command.trim().to_string();
+        if command.contains(['\n', '\r']) {
+            return Err(format!("命令中不能包含换行: {:?}", command));
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        //加入队列和发送在同一把锁下完成，保证队列的顺序与发送的顺序一致
+        let mut pending = self.pending.lock().unwrap();
+        if pending.closed {
+            return Err("引擎已退出".to_string());
+        }
         self.cmd_tx
-            .send(command.trim().to_string())
-            .map_err(|e| e.to_string())
-    }
-
-    pub fn send_kata_analyze(&self) -> Result<(), String> {
-        self.send_command("kata-analyze 15 pvVisits true".to_string())
+            .send(format!("{} {}", id, command))
+            .map_err(|e| e.to_string())?;
+        pending.commands.push_back(Pending { id, command, tx });
+        Ok(ResponseHandle { id, rx })
     }
 
-    pub fn exit(&mut self) -> Result<(), String> {
+    fn exit(&mut self) -> Result<(), String> {
         let _ = self
             .cmd_tx
             .send("rboard gtp exit".to_string())
@@ -150,7 +340,42 @@ impl Drop for GTP {
     }
 }
 
-fn debug(msg: String) {
+/// 解析回复的第一行 `=[id] body` 或 `?[id] body`。
+///
+/// # 返回值
+/// `(是否成功, 编号, 内容)`，不是回复时返回 `None`。
+///
+/// ```
+/// use rboard::engine::gtp::parse_response_line;
+///
+/// assert_eq!(parse_response_line("= H8"), Some((true, None, "H8")));
+/// assert_eq!(parse_response_line("=12 H8"), Some((true, Some(12), "H8")));
+/// assert_eq!(parse_response_line("?3 unknown command"), Some((false, Some(3), "unknown command")));
+/// assert_eq!(parse_response_line("=5"), Some((true, Some(5), "")));
+/// assert_eq!(parse_response_line("info move H8"), None);
+/// ```
+pub fn parse_response_line(line: &str) -> Option<(bool, Option<u32>, &str)> {
+    let success = match line.chars().next()? {
+        '=' => true,
+        '?' => false,
+        _ => return None,
+    };
+    let rest = &line[1..];
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let id = rest[..digits].parse().ok();
+    Some((success, id, rest[digits..].trim()))
+}
+
+/// 设置棋盘大小的命令，正方形棋盘使用 `boardsize`，长方形棋盘使用 `rectangular_boardsize`。
+pub fn boardsize_command((x, y): (u32, u32)) -> String {
+    if x == y {
+        format!("boardsize {}", x)
+    } else {
+        format!("rectangular_boardsize {} {}", x, y)
+    }
+}
+
+pub(crate) fn debug(msg: String) {
     let current_time = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -158,7 +383,7 @@ fn debug(msg: String) {
     println!("[D] {} GTP Engine: {}", current_time, msg);
 }
 
-fn edebug(msg: String) {
+pub(crate) fn edebug(msg: String) {
     let current_time = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -166,7 +391,10 @@ fn edebug(msg: String) {
     eprintln!("[E] {} GTP Engine: {}", current_time, msg);
 }
 
-fn spawn_child_process(engine_path: &str, engine_args: &str) -> Result<Popen, PopenError> {
+pub(crate) fn spawn_child_process(
+    engine_path: &str,
+    engine_args: &str,
+) -> Result<Popen, PopenError> {
     let process = Exec::cmd(engine_path)
         .args(&parse_args(&engine_args.to_string()))
         .stdin(Redirection::Pipe) // 管道输入
@@ -187,3 +415,105 @@ fn parse_args<'a>(args: &'a String) -> Vec<&'a str> {
     let a: Vec<&'a str> = args.split_whitespace().collect();
     a
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(
+        commands: &[(u32, &str)],
+    ) -> (Mutex<PendingQueue>, Vec<oneshot::Receiver<Response>>) {
+        let mut queue = PendingQueue::default();
+        let mut receivers = Vec::new();
+        for &(id, command) in commands {
+            let (tx, rx) = oneshot::channel();
+            queue.commands.push_back(Pending {
+                id,
+                command: command.to_string(),
+                tx,
+            });
+            receivers.push(rx);
+        }
+        (Mutex::new(queue), receivers)
+    }
+
+    fn feed(
+        parser: &mut StdoutParser,
+        pending: &Mutex<PendingQueue>,
+        lines: &[&str],
+    ) -> Vec<EngineOutput> {
+        lines
+            .iter()
+            .filter_map(|line| parser.line(line, pending))
+            .collect()
+    }
+
+    #[test]
+    fn analyze_reply_then_unnumbered_reply() {
+        let (pending, mut receivers) = pending(&[(1, "kata-analyze 50"), (2, "name")]);
+        let mut parser = StdoutParser::default();
+        let outputs = feed(
+            &mut parser,
+            &pending,
+            &[
+                "=1",
+                "info move H8 visits 10 winrate 0.5 pv H8 I9",
+                "info move I9 visits 4 winrate 0.4 pv I9",
+                "",
+                "= KataGo",
+                "",
+            ],
+        );
+        assert_eq!(outputs.len(), 4);
+        assert!(matches!(&outputs[0], EngineOutput::Info(s) if s.starts_with("info move H8")));
+        assert!(matches!(&outputs[1], EngineOutput::Info(s) if s.starts_with("info move I9")));
+        match &outputs[2] {
+            EngineOutput::Response(r) => {
+                assert_eq!(
+                    (r.id, r.name(), r.success, r.body.as_str()),
+                    (1, "kata-analyze", true, "")
+                );
+            }
+            output => panic!("{:?}", output),
+        }
+        //不带编号的回复按顺序对应下一条命令
+        match &outputs[3] {
+            EngineOutput::Response(r) => {
+                assert_eq!(
+                    (r.id, r.name(), r.success, r.body.as_str()),
+                    (2, "name", true, "KataGo")
+                );
+            }
+            output => panic!("{:?}", output),
+        }
+        assert_eq!(receivers[0].try_recv().unwrap().unwrap().id, 1);
+        assert_eq!(receivers[1].try_recv().unwrap().unwrap().body, "KataGo");
+        assert!(pending.lock().unwrap().commands.is_empty());
+    }
+
+    #[test]
+    fn multi_line_reply_and_stray_output() {
+        let (pending, _receivers) = pending(&[(3, "list_commands")]);
+        let mut parser = StdoutParser::default();
+        let outputs = feed(
+            &mut parser,
+            &pending,
+            &[
+                "loading model",
+                "=3 name",
+                "play",
+                "genmove",
+                "",
+                "= extra",
+                "",
+            ],
+        );
+        assert_eq!(outputs.len(), 3);
+        assert!(matches!(&outputs[0], EngineOutput::Log(s) if s == "loading model"));
+        assert!(
+            matches!(&outputs[1], EngineOutput::Response(r) if r.body == "name\nplay\ngenmove")
+        );
+        //没有命令等待回复
+        assert!(matches!(&outputs[2], EngineOutput::Log(s) if s == "= extra"));
+    }
+}
@@ -0,0 +1,396 @@
+//! 引擎对战：两个 GTP 引擎轮流 `genmove`，对方用 `play` 跟上，
+//! 交换先后手下多局，统计胜负和与 Elo 差。
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+
+use iced::futures::channel::mpsc;
+
+use crate::{
+    chessboard::{
+        Move,
+        chessboard_trait::{ChessboardTrait, Player},
+        get_chessboard,
+    },
+    engine::{
+        Engine,
+        engine_args::EngineArgs,
+        gtp::{EngineOutput, boardsize_command},
+        start_engine,
+    },
+    game_tree::GameTree,
+    sgf,
+};
+
+/// 对战中的一个引擎，按顺序发送命令并等待回复。
+pub struct MatchEngine {
+    pub name: String,
+    engine: Box<dyn Engine>,
+}
+
+impl MatchEngine {
+    pub fn start(args: &EngineArgs) -> Result<Self, String> {
+        //对战只需要每条命令的回复，输出流的接收端直接丢弃
+        let (tx, _) = mpsc::channel::<EngineOutput>(1);
+        let engine = start_engine(args, Arc::new(Mutex::new(tx)))?;
+        Ok(MatchEngine {
+            name: args.name.clone(),
+            engine,
+        })
+    }
+
+    /// 发送一条命令并等待回复。
+    ///
+    /// # 返回值
+    /// - `Ok(String)`：`=` 之后的内容，多行回复用 `\n` 连接。
+    /// - `Err(String)`：引擎回复 `?` 或者已经退出。
+    pub fn command(&mut self, cmd: &str) -> Result<String, String> {
+        self.engine
+            .send_command(cmd.to_string())
+            .and_then(|handle| handle.wait())
+            .map_err(|e| format!("{} {}", self.name, e))
+    }
+}
+
+/// 对战设置。
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    /// 棋盘的唯一标识，见 `chessboard::get_all_board_names`
+    pub board: String,
+    /// 棋盘尺寸，`None` 时使用默认尺寸
+    pub size: Option<(u32, u32)>,
+    /// 对局数
+    pub games: u32,
+    /// 开局库，每个开局为若干手棋的坐标，例如 `["H8", "I9"]`。
+    /// 每个开局连续下两局，双方各执一次黑
+    pub openings: Vec<Vec<String>>,
+}
+
+/// 一局棋的结果。
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    /// 第几局，从 0 开始
+    pub index: u32,
+    pub black: String,
+    pub white: String,
+    /// 第一个引擎是否执黑，偶数局执黑，奇数局执白
+    pub first_black: bool,
+    /// 胜者，`None` 为和棋
+    pub winner: Option<Player>,
+    /// 结果，与 SGF 的 `RE` 相同，例如 `"B+"`、`"W+R"`、`"Draw"`
+    pub result: String,
+    /// 棋谱
+    pub sgf: String,
+}
+
+/// 从第一个引擎的角度统计的胜负和。
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl MatchScore {
+    pub fn add(&mut self, record: &GameRecord) {
+        match record.winner {
+            None => self.draws += 1,
+            Some(Player::Black) if record.first_black => self.wins += 1,
+            Some(Player::White) if !record.first_black => self.wins += 1,
+            Some(_) => self.losses += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// 得分率，胜 1 分、和 0.5 分。
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// 第一个引擎比第二个引擎高出的 Elo，以及 95% 置信区间的半宽。
+    /// 全胜或全负时 Elo 差为无穷大。
+    ///
+    /// # 示例
+    /// ```
+    /// use rboard::engine::match_runner::MatchScore;
+    ///
+    /// let score = MatchScore { wins: 30, losses: 20, draws: 0 };
+    /// let (elo, error) = score.elo();
+    /// assert!((elo - 70.4).abs() < 0.1);
+    /// assert!(error > 90.0 && error < 110.0);
+    /// ```
+    pub fn elo(&self) -> (f64, f64) {
+        let n = self.games() as f64;
+        if n == 0.0 {
+            return (0.0, f64::INFINITY);
+        }
+        let s = self.score();
+        let variance = (self.wins as f64 * (1.0 - s).powi(2)
+            + self.losses as f64 * s.powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2))
+            / n;
+        let margin = 1.96 * (variance / n).sqrt();
+        let (low, high) = (elo_from_score(s - margin), elo_from_score(s + margin));
+        (elo_from_score(s), (high - low) / 2.0)
+    }
+
+    /// H1（Elo 差为 `elo1`）相对 H0（Elo 差为 `elo0`）的对数似然比，用正态分布近似计算。
+    ///
+    /// # 示例
+    /// ```
+    /// use rboard::engine::match_runner::MatchScore;
+    ///
+    /// let score = MatchScore { wins: 300, losses: 200, draws: 0 };
+    /// let llr = score.llr(0.0, 10.0);
+    /// assert!(llr > 2.7 && llr < 2.9);
+    /// ```
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let n = self.games() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let s = self.score();
+        //每局得分的平方的期望，胜 1、和 0.25、负 0
+        let m2 = (self.wins as f64 + self.draws as f64 / 4.0) / n;
+        let variance = (m2 - s * s) / n;
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (score_from_elo(elo0), score_from_elo(elo1));
+        (s1 - s0) * (2.0 * s - s0 - s1) / (2.0 * variance)
+    }
+}
+
+/// 序贯概率比检验（SPRT）：H0 为第一个引擎高出 `elo0`，H1 为高出 `elo1`，
+/// `alpha`、`beta` 为两类错误的概率。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// 对数似然比的下界和上界。
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// 根据当前比分判断能否停止。
+    ///
+    /// # 返回值
+    /// - `Some(true)`：越过上界，接受 H1。
+    /// - `Some(false)`：越过下界，接受 H0。
+    /// - `None`：需要继续对局。
+    pub fn test(&self, score: &MatchScore) -> Option<bool> {
+        let llr = score.llr(self.elo0, self.elo1);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(true)
+        } else if llr <= lower {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// Elo 差对应的期望得分率。
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// 得分率对应的 Elo 差。
+pub fn elo_from_score(score: f64) -> f64 {
+    if score <= 0.0 {
+        f64::NEG_INFINITY
+    } else if score >= 1.0 {
+        f64::INFINITY
+    } else {
+        -400.0 * (1.0 / score - 1.0).log10()
+    }
+}
+
+/// 解析开局库：每行一个开局，坐标之间用空格或逗号分隔，`#` 开头的行为注释。
+pub fn parse_openings(s: &str) -> Vec<Vec<String>> {
+    s.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.split([' ', ',', '\t'])
+                .filter(|mv| !mv.is_empty())
+                .map(|mv| mv.to_string())
+                .collect()
+        })
+        .collect()
+}
+
+/// 第 `index` 局使用的开局。
+pub fn opening_for(openings: &[Vec<String>], index: u32) -> &[String] {
+    if openings.is_empty() {
+        &[]
+    } else {
+        &openings[(index / 2) as usize % openings.len()]
+    }
+}
+
+/// 启动两个引擎，按 `config` 下完全部对局，每下完一局调用一次 `on_game`。
+/// `stop` 被设为 `true` 时在下一手之前结束对战。
+pub fn run_match(
+    config: &MatchConfig,
+    first: &EngineArgs,
+    second: &EngineArgs,
+    stop: &AtomicBool,
+    mut on_game: impl FnMut(GameRecord),
+) -> Result<(), String> {
+    let mut first = MatchEngine::start(first)?;
+    let mut second = MatchEngine::start(second)?;
+    for index in 0..config.games {
+        let opening = opening_for(&config.openings, index);
+        let first_black = index % 2 == 0;
+        let (black, white) = if first_black {
+            (&mut first, &mut second)
+        } else {
+            (&mut second, &mut first)
+        };
+        let mut record = play_game(&config.board, config.size, opening, black, white, stop)?;
+        record.index = index;
+        record.first_black = first_black;
+        on_game(record);
+    }
+    Ok(())
+}
+
+/// 下一局棋。开局中的棋步由双方直接摆上，之后轮到谁就让谁 `genmove`。
+/// 引擎下出非法着法时判负，超过棋盘格数三倍手数时判和。
+pub fn play_game(
+    board: &str,
+    size: Option<(u32, u32)>,
+    opening: &[String],
+    black: &mut MatchEngine,
+    white: &mut MatchEngine,
+    stop: &AtomicBool,
+) -> Result<GameRecord, String> {
+    let mut chessboard = get_chessboard(board.to_string(), size);
+    let (w, h) = chessboard.get_length();
+    let mut tree = GameTree::new();
+    tree.info.black = black.name.clone();
+    tree.info.white = white.name.clone();
+    tree.info.komi = chessboard.get_komi();
+    for engine in [&mut *black, &mut *white] {
+        engine.command(&boardsize_command((w, h)))?;
+        engine.command("clear_board")?;
+        //规则和贴目不是所有引擎都支持
+        if let Some(rules) = chessboard.get_rules() {
+            let _ = engine.command(&format!("kata-set-rules {}", rules));
+        }
+        if let Some(komi) = chessboard.get_komi() {
+            let _ = engine.command(&format!("komi {}", komi));
+        }
+    }
+    for mv in opening {
+        let cmds = chessboard
+            .get_move(mv)
+            .and_then(|mv| play(chessboard.as_mut(), &mut tree, mv))
+            .ok_or(format!("开局中的非法着法: {}", mv))?;
+        for engine in [&mut *black, &mut *white] {
+            for cmd in &cmds {
+                engine.command(cmd)?;
+            }
+        }
+    }
+
+    let mut moves = 0;
+    let (winner, reason) = loop {
+        if let Some(result) = chessboard.get_result() {
+            break (result.winner, "");
+        }
+        if moves > w * h * 3 {
+            break (None, "");
+        }
+        if stop.load(Ordering::Relaxed) {
+            return Err("对战已停止".to_string());
+        }
+        let player = chessboard.get_player();
+        let (mover, other) = match player {
+            Player::Black => (&mut *black, &mut *white),
+            Player::White => (&mut *white, &mut *black),
+        };
+        let reply = mover.command(&format!("genmove {}", player.gtp()))?;
+        moves += 1;
+        let mv = chessboard.get_move(&reply);
+        if mv == Some(Move::Resign) {
+            tree.resign(player);
+            break (Some(player.other()), "R");
+        }
+        let Some(cmds) = mv.and_then(|mv| play(chessboard.as_mut(), &mut tree, mv)) else {
+            tree.set_comment(format!("{} 非法着法: {}", mover.name, reply));
+            break (Some(player.other()), "F");
+        };
+        //落子方已经记下了自己的这一手，之后自动停的一手双方都要知道
+        other.command(&cmds[0])?;
+        for cmd in &cmds[1..] {
+            mover.command(cmd)?;
+            other.command(cmd)?;
+        }
+    };
+
+    let result = match winner {
+        Some(player) => format!("{}+{}", player.gtp(), reason),
+        None => "Draw".to_string(),
+    };
+    tree.info.result = result.clone();
+    Ok(GameRecord {
+        index: 0,
+        black: black.name.clone(),
+        white: white.name.clone(),
+        first_black: true,
+        winner,
+        result,
+        sgf: sgf::write(board, (w, h), &tree),
+    })
+}
+
+//在棋盘和棋谱上下一手，返回需要发给引擎的命令，包括之后自动停的一手
+fn play(
+    chessboard: &mut dyn ChessboardTrait,
+    tree: &mut GameTree,
+    mv: Move,
+) -> Option<Vec<String>> {
+    let player = chessboard.get_player();
+    let cmd = match mv {
+        Move::Place(x, y) => {
+            let cmd = chessboard.go(x as i32, y as i32)?;
+            tree.play(player, x as i32, y as i32);
+            cmd
+        }
+        Move::Pass => {
+            let cmd = chessboard.pass()?;
+            tree.pass(player);
+            cmd
+        }
+        Move::Resign => return None,
+    };
+    let mut cmds = vec![cmd];
+    while chessboard.must_pass() {
+        let player = chessboard.get_player();
+        let Some(pass) = chessboard.pass() else {
+            break;
+        };
+        tree.pass(player);
+        cmds.push(pass);
+    }
+    Some(cmds)
+}
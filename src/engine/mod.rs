@@ -1,5 +1,73 @@
 pub mod analyze;
 pub mod analyzes_table;
+pub mod capabilities;
 pub mod engine_args;
 pub mod engine_paths;
 pub mod gtp;
+pub mod match_runner;
+pub mod piskvork;
+
+use std::sync::{Arc, Mutex};
+
+use iced::futures::channel::mpsc::Sender;
+
+use crate::{
+    chessboard::chessboard_trait::Player,
+    engine::{
+        capabilities::AnalyzeCommand,
+        engine_args::{EngineArgs, Protocol},
+        gtp::{EngineOutput, GTP, ResponseHandle, boardsize_command},
+        piskvork::Piskvork,
+    },
+};
+
+/// 引擎后端。界面和对战都用 GTP 命令与引擎交互，
+/// 不使用 GTP 的引擎（例如 `Piskvork`）负责把 GTP 命令翻译成自己的协议。
+pub trait Engine: Send {
+    /// 发送一条 GTP 命令，返回等待回复的句柄。
+    ///
+    /// 回复同时会作为 `EngineOutput::Response` 出现在输出流中。
+    fn send_command(&self, command: String) -> Result<ResponseHandle, String>;
+
+    /// 关闭引擎进程。
+    fn exit(&mut self) -> Result<(), String>;
+
+    /// 用 `command` 分析 `player` 的下一手。显式指定颜色，摆棋之后引擎不一定知道轮到谁。
+    fn send_analyze(
+        &self,
+        command: AnalyzeCommand,
+        player: Player,
+    ) -> Result<ResponseHandle, String> {
+        self.send_command(command.command(player, 15))
+    }
+
+    /// 用 `command` 的 genmove_analyze 替 `player` 下一手，思考期间输出 `info`，回复 `= play G3`。
+    fn send_genmove_analyze(
+        &self,
+        command: AnalyzeCommand,
+        player: Player,
+    ) -> Result<ResponseHandle, String> {
+        self.send_command(command.genmove_command(player, 15))
+    }
+
+    /// 让引擎替 `player` 下一手，引擎回复 `= G3`、`= pass` 或 `= resign`，并且自己记下这一手。
+    fn send_genmove(&self, player: Player) -> Result<ResponseHandle, String> {
+        self.send_command(format!("genmove {}", player.gtp()))
+    }
+
+    /// 设置棋盘大小，见 `boardsize_command`。
+    fn send_boardsize(&self, size: (u32, u32)) -> Result<ResponseHandle, String> {
+        self.send_command(boardsize_command(size))
+    }
+}
+
+/// 按 `args.protocol` 启动引擎，输出发送到 `data_tx`。
+pub fn start_engine(
+    args: &EngineArgs,
+    data_tx: Arc<Mutex<Sender<EngineOutput>>>,
+) -> Result<Box<dyn Engine>, String> {
+    match args.protocol {
+        Protocol::Gtp => Ok(Box::new(GTP::start(&args.path, &args.args, data_tx)?)),
+        Protocol::Piskvork => Ok(Box::new(Piskvork::start(&args.path, &args.args, data_tx)?)),
+    }
+}
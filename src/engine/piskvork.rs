@@ -0,0 +1,832 @@
+//! Gomocup 的 Piskvork 协议引擎，例如 Rapfi、Embryo 以及 Yixin-Board 使用的引擎。
+//!
+//! 界面只发送 GTP 命令，这里把它们翻译成 Piskvork 协议：落子、悔棋和摆棋只记在本地，
+//! 需要引擎落子或分析时用 `BOARD` 发送整个局面；引擎 `MESSAGE` 输出的搜索信息
+//! 翻译成 `kata-analyze` 格式的 `info` 行，显示在分析表格和棋盘上。
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    thread,
+};
+
+use iced::futures::{
+    SinkExt,
+    channel::{mpsc::Sender, oneshot},
+    executor::block_on,
+};
+use subprocess::Popen;
+
+use crate::{
+    chessboard::{Move, chessboard_trait::Player, get_coord, get_move},
+    engine::{
+        Engine,
+        gtp::{EngineOutput, Response, ResponseHandle, debug, edebug, spawn_child_process},
+    },
+};
+
+//引擎落子的思考时间（毫秒）
+const MOVE_TIME: u32 = 5000;
+//分析时每次搜索的时间，搜索结束后从当前局面重新开始，直到界面停止分析。
+//不支持 YXSTOP 的引擎最多在这么久之后才响应下一条命令
+const ANALYZE_TIME: u32 = 3000;
+
+//翻译之后支持的 GTP 命令，作为 list_commands 的回复
+const COMMANDS: [&str; 15] = [
+    "protocol_version",
+    "name",
+    "version",
+    "list_commands",
+    "boardsize",
+    "rectangular_boardsize",
+    "clear_board",
+    "play",
+    "undo",
+    "set_position",
+    "genmove",
+    "kata-genmove_analyze",
+    "kata-analyze",
+    "kata-set-rules",
+    "stop",
+];
+
+pub struct Piskvork {
+    cmd_tx: std::sync::mpsc::Sender<String>,
+    child: Popen,
+    cmd_handler: Option<thread::JoinHandle<()>>,
+    output_handler: Option<thread::JoinHandle<()>>,
+    state: Arc<Mutex<State>>,
+    //发往输出流的内容，由转发线程按顺序送到界面，回复不会因为输出流已满而丢失
+    out_tx: std::sync::mpsc::Sender<EngineOutput>,
+    next_id: AtomicU32,
+}
+
+impl Piskvork {
+    pub fn start(
+        engine_path: &str,
+        engine_args: &str,
+        data_tx: Arc<Mutex<Sender<EngineOutput>>>,
+    ) -> Result<Self, String> {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel::<String>();
+
+        let mut child = spawn_child_process(engine_path, engine_args)
+            .map_err(|err| format!("无法启动进程: {}", err))?;
+        debug(format!("子进程已启动 (PID: {})", child.pid().unwrap_or(0)));
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or("无法获取子进程的标准输入".to_string())?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or("无法获取子进程的标准输出".to_string())?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or("无法获取子进程的标准错误".to_string())?;
+
+        // 启动命令发送线程
+        let cmd_handler = thread::spawn(move || {
+            for command in cmd_rx {
+                if command == "rboard piskvork exit" {
+                    break;
+                }
+                if let Err(e) = stdin
+                    .write_all(format!("{}\n", command).as_bytes())
+                    .and_then(|_| stdin.flush())
+                {
+                    eprintln!("写入命令失败: {}", e);
+                    break;
+                }
+            }
+            eprintln!("命令线程已退出");
+        });
+
+        // 启动输出转发线程，界面线程和 stdout 线程都不需要等待输出流
+        let (out_tx, out_rx) = std::sync::mpsc::channel::<EngineOutput>();
+        let data_tx_out = Arc::clone(&data_tx);
+        thread::spawn(move || {
+            for output in out_rx {
+                if block_on(data_tx_out.lock().unwrap().send(output)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let state_out = Arc::clone(&state);
+        let cmd_tx_out = cmd_tx.clone();
+        let out_tx_out = out_tx.clone();
+        let data_tx_err = Arc::clone(&data_tx);
+        // 启动输出读取线程
+        let output_handler = thread::spawn(move || {
+            let stdout_thread = thread::spawn(move || {
+                let reader = BufReader::new(stdout);
+                for line in reader.lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(e) => {
+                            edebug(format!("读取输出错误: {}", e));
+                            continue;
+                        }
+                    };
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let mut state = state_out.lock().unwrap();
+                    let outputs = state.receive(line);
+                    //在同一把锁下发送，保证与 send_command 发送的顺序一致
+                    for line in state.outbox.drain(..) {
+                        let _ = cmd_tx_out.send(line);
+                    }
+                    for output in outputs {
+                        let _ = out_tx_out.send(output);
+                    }
+                }
+                //引擎退出，等待中的命令不会再有回复
+                let mut state = state_out.lock().unwrap();
+                state.closed = true;
+                state.pending.clear();
+            });
+
+            let stderr_thread = thread::spawn(move || {
+                let reader = BufReader::new(stderr);
+                for line in reader.lines() {
+                    match line {
+                        Ok(err) => {
+                            let _ =
+                                block_on(data_tx_err.lock().unwrap().send(EngineOutput::Log(err)));
+                        }
+                        Err(e) => edebug(format!("读取错误输出错误: {}", e)),
+                    }
+                }
+            });
+
+            stdout_thread.join().expect("stdout 线程崩溃");
+            stderr_thread.join().expect("stderr 线程崩溃");
+        });
+
+        Ok(Piskvork {
+            cmd_tx,
+            child,
+            cmd_handler: Some(cmd_handler),
+            output_handler: Some(output_handler),
+            state,
+            out_tx,
+            next_id: AtomicU32::new(1),
+        })
+    }
+}
+
+impl Engine for Piskvork {
+    /// 翻译并发送一条 GTP 命令。只改变本地局面的命令立即回复，
+    /// 其余的命令等引擎回复 `OK`、`ERROR`、`ABOUT` 的信息或者一手棋。
+    fn send_command(&self, command: String) -> Result<ResponseHandle, String> {
+        let command = command.trim().to_string();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        //翻译、发送和加入队列在同一把锁下完成，保证队列的顺序与发送的顺序一致
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err("引擎已退出".to_string());
+        }
+        let send = |lines: Vec<String>| -> Result<(), String> {
+            for line in lines {
+                self.cmd_tx.send(line).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        };
+        match state.translate(&command) {
+            Action::Remote(lines, kind) => {
+                send(lines)?;
+                state.pending.push_back(Pending {
+                    id,
+                    command,
+                    kind,
+                    tx,
+                });
+            }
+            Action::Local(lines, result) => {
+                send(lines)?;
+                let (success, body) = match result {
+                    Ok(body) => (true, body),
+                    Err(body) => (false, body),
+                };
+                let response = Response {
+                    id,
+                    command,
+                    success,
+                    body,
+                };
+                let _ = tx.send(response.clone());
+                let _ = self.out_tx.send(EngineOutput::Response(response));
+            }
+        }
+        Ok(ResponseHandle::new(id, rx))
+    }
+
+    fn exit(&mut self) -> Result<(), String> {
+        let _ = self.cmd_tx.send("END".to_string());
+        let _ = self.cmd_tx.send("rboard piskvork exit".to_string());
+        if self.child.poll().is_some() {
+            debug("子进程已退出".to_string());
+        } else {
+            debug("终止子进程...".to_string());
+            if let Err(e) = self.child.terminate() {
+                edebug(format!("终止子进程失败: {}", e));
+            }
+        }
+
+        let _ = self.child.kill();
+
+        if let Some(handler) = self.cmd_handler.take() {
+            let _ = handler.join();
+        }
+        if let Some(handler) = self.output_handler.take() {
+            let _ = handler.join();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Piskvork {
+    fn drop(&mut self) {
+        let _ = self.exit();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SearchMode {
+    //genmove，回复 `= H8`
+    Genmove,
+    //kata-genmove_analyze，回复 `= play H8`，思考期间输出 info
+    GenmoveAnalyze,
+    //kata-analyze，引擎给出的一手不落在棋盘上
+    Analyze,
+}
+
+//等待引擎回复的命令类型
+enum Kind {
+    //START、RECTSTART，回复 OK 或 ERROR
+    Ack,
+    //ABOUT，回复 name="Rapfi", version="..."，取其中一项
+    About(&'static str),
+    //BOARD，回复引擎的一手 `x,y`；`version` 为发送时的局面版本
+    Search {
+        player: Player,
+        mode: SearchMode,
+        version: u64,
+    },
+}
+
+struct Pending {
+    id: u32,
+    command: String,
+    kind: Kind,
+    tx: oneshot::Sender<Response>,
+}
+
+//一条 GTP 命令的翻译结果
+enum Action {
+    //发送这些行（可以没有）后直接回复
+    Local(Vec<String>, Result<String, String>),
+    //发送这些行，等待引擎回复
+    Remote(Vec<String>, Kind),
+}
+
+struct State {
+    size: (u32, u32),
+    //摆出和下过的棋子，按落子顺序
+    stones: Vec<(u32, u32, Player)>,
+    //局面每改变一次加一，搜索期间局面改变时不记下引擎的这一手
+    version: u64,
+    //INFO rule 的值，START 之后需要重新发送
+    rule: Option<u32>,
+    closed: bool,
+    pending: VecDeque<Pending>,
+    realtime: Realtime,
+    //界面发送了 stop，分析的这次搜索结束后不再重新开始
+    stopped: bool,
+    //处理引擎输出时需要发送给引擎的行
+    outbox: Vec<String>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            size: (15, 15),
+            stones: vec![],
+            version: 0,
+            rule: None,
+            closed: false,
+            pending: VecDeque::new(),
+            realtime: Realtime::default(),
+            stopped: false,
+            outbox: vec![],
+        }
+    }
+}
+
+impl State {
+    fn translate(&mut self, command: &str) -> Action {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        let Some((&name, args)) = words.split_first() else {
+            return Action::Local(vec![], Err("empty command".to_string()));
+        };
+        let ok = |body: &str| Action::Local(vec![], Ok(body.to_string()));
+        let err = |body: &str| Action::Local(vec![], Err(body.to_string()));
+        match name {
+            "protocol_version" => ok("2"),
+            "name" => Action::Remote(vec!["ABOUT".to_string()], Kind::About("name")),
+            "version" => Action::Remote(vec!["ABOUT".to_string()], Kind::About("version")),
+            "list_commands" => ok(&COMMANDS.join("\n")),
+            "boardsize" | "rectangular_boardsize" => {
+                let size = args
+                    .iter()
+                    .map(|a| a.parse::<u32>())
+                    .collect::<Result<Vec<_>, _>>();
+                let (w, h) = match size.as_deref() {
+                    Ok(&[n]) => (n, n),
+                    Ok(&[w, h]) => (w, h),
+                    _ => return err("invalid size"),
+                };
+                self.size = (w, h);
+                self.clear();
+                let mut lines = vec![if w == h {
+                    format!("START {}", w)
+                } else {
+                    format!("RECTSTART {},{}", w, h)
+                }];
+                //思考时间由每一手的 timeout_turn 决定
+                lines.push("INFO timeout_match 0".to_string());
+                if let Some(rule) = self.rule {
+                    lines.push(format!("INFO rule {}", rule));
+                }
+                Action::Remote(lines, Kind::Ack)
+            }
+            "clear_board" => {
+                self.clear();
+                ok("")
+            }
+            "play" => match self.parse_stones(args).as_deref() {
+                Some(&[stone]) => {
+                    self.stones.push(stone);
+                    self.version += 1;
+                    ok("")
+                }
+                //五子棋没有停一手，颜色由 BOARD 中的每个棋子决定，不需要告诉引擎
+                Some(&[]) if args.len() == 2 => ok(""),
+                _ => err("illegal move"),
+            },
+            "undo" => {
+                if self.stones.pop().is_none() {
+                    return err("cannot undo");
+                }
+                self.version += 1;
+                ok("")
+            }
+            "set_position" => match self.parse_stones(args) {
+                Some(stones) => {
+                    self.stones = stones;
+                    self.version += 1;
+                    ok("")
+                }
+                None => err("illegal position"),
+            },
+            "kata-set-rules" => match args.first().and_then(|r| rule_code(r)) {
+                Some(rule) => {
+                    self.rule = Some(rule);
+                    Action::Local(vec![format!("INFO rule {}", rule)], Ok(String::new()))
+                }
+                None => err("unknown rules"),
+            },
+            "genmove" | "kata-genmove_analyze" | "kata-analyze" => {
+                let Some(player) = args.first().and_then(|c| Player::from_gtp(c)) else {
+                    return err("invalid color");
+                };
+                let (mode, time) = match name {
+                    "genmove" => (SearchMode::Genmove, MOVE_TIME),
+                    "kata-genmove_analyze" => (SearchMode::GenmoveAnalyze, MOVE_TIME),
+                    _ => (SearchMode::Analyze, ANALYZE_TIME),
+                };
+                let mut lines = vec![];
+                //上一次搜索还没有结束时先让引擎停下，不支持 YXSTOP 的引擎会等到超时
+                if self.searching() {
+                    lines.push("YXSTOP".to_string());
+                }
+                lines.extend(self.board_lines(player, time));
+                self.stopped = false;
+                let version = self.version;
+                Action::Remote(
+                    lines,
+                    Kind::Search {
+                        player,
+                        mode,
+                        version,
+                    },
+                )
+            }
+            "stop" => {
+                let lines = if self.searching() {
+                    self.stopped = true;
+                    vec!["YXSTOP".to_string()]
+                } else {
+                    vec![]
+                };
+                Action::Local(lines, Ok(String::new()))
+            }
+            _ => err("unknown command"),
+        }
+    }
+
+    /// 处理引擎输出的一行，返回需要发送到输出流的内容。
+    fn receive(&mut self, line: &str) -> Vec<EngineOutput> {
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let log = vec![EngineOutput::Log(line.to_string())];
+        match word.to_ascii_uppercase().as_str() {
+            "MESSAGE" => {
+                let mut outputs = log;
+                if self.analyzing() && self.realtime.update(rest.trim(), self.size) {
+                    outputs.extend(self.realtime.info(self.size.1).map(EngineOutput::Info));
+                }
+                outputs
+            }
+            "DEBUG" | "UNKNOWN" | "SUGGEST" => log,
+            "OK" if matches!(
+                self.pending.front(),
+                Some(Pending {
+                    kind: Kind::Ack,
+                    ..
+                })
+            ) =>
+            {
+                self.resolve(Ok(String::new()))
+            }
+            "ERROR" if !self.pending.is_empty() => {
+                if self.searching() {
+                    self.realtime = Realtime::default();
+                }
+                self.resolve(Err(rest.trim().to_string()))
+            }
+            _ => match self.pending.front().map(|p| &p.kind) {
+                Some(Kind::About(key)) => {
+                    let value = about_value(line, key);
+                    self.resolve(Ok(value))
+                }
+                Some(&Kind::Search { .. }) => match parse_point(line) {
+                    Some(point) => self.finish_search(point),
+                    None => log,
+                },
+                _ => log,
+            },
+        }
+    }
+
+    //引擎给出了一手棋，落子时记在局面上
+    fn finish_search(&mut self, (x, y): (u32, u32)) -> Vec<EngineOutput> {
+        self.realtime = Realtime::default();
+        let Some(Pending {
+            kind:
+                Kind::Search {
+                    player,
+                    mode,
+                    version,
+                },
+            ..
+        }) = self.pending.front()
+        else {
+            return vec![];
+        };
+        let (player, mode, version) = (*player, *mode, *version);
+        let (w, h) = self.size;
+        if x >= w || y >= h {
+            return self.resolve(Err(format!("illegal move {},{}", x, y)));
+        }
+        let coord = get_coord(x as i32, y as i32, h);
+        let body = match mode {
+            //没有停止分析、局面没有改变、也没有其他命令在等待时继续分析
+            SearchMode::Analyze
+                if !self.stopped && version == self.version && self.pending.len() == 1 =>
+            {
+                let lines = self.board_lines(player, ANALYZE_TIME);
+                self.outbox.extend(lines);
+                return vec![];
+            }
+            SearchMode::Analyze => return self.resolve(Ok(String::new())),
+            SearchMode::Genmove => coord,
+            SearchMode::GenmoveAnalyze => format!("play {}", coord),
+        };
+        //局面已经改变时这一手已经过时，界面也会丢弃这条回复
+        if version == self.version {
+            self.stones.push((x, y, player));
+            self.version += 1;
+        }
+        self.resolve(Ok(body))
+    }
+
+    //回复队首的命令
+    fn resolve(&mut self, result: Result<String, String>) -> Vec<EngineOutput> {
+        let Some(pending) = self.pending.pop_front() else {
+            return vec![];
+        };
+        let (success, body) = match result {
+            Ok(body) => (true, body),
+            Err(body) => (false, body),
+        };
+        let response = Response {
+            id: pending.id,
+            command: pending.command,
+            success,
+            body,
+        };
+        let _ = pending.tx.send(response.clone());
+        vec![EngineOutput::Response(response)]
+    }
+
+    //让引擎为 `player` 搜索当前局面，思考 `time` 毫秒
+    fn board_lines(&self, player: Player, time: u32) -> Vec<String> {
+        let mut lines = vec![format!("INFO timeout_turn {}", time), "BOARD".to_string()];
+        for &(x, y, p) in &self.stones {
+            //1 为将要落子的一方，2 为对方
+            lines.push(format!("{},{},{}", x, y, if p == player { 1 } else { 2 }));
+        }
+        lines.push("DONE".to_string());
+        lines
+    }
+
+    fn clear(&mut self) {
+        self.stones.clear();
+        self.version += 1;
+    }
+
+    //是否有还没有结束的搜索
+    fn searching(&self) -> bool {
+        self.pending
+            .iter()
+            .any(|p| matches!(p.kind, Kind::Search { .. }))
+    }
+
+    //当前的搜索是否需要输出分析信息
+    fn analyzing(&self) -> bool {
+        matches!(
+            self.pending.front(),
+            Some(Pending {
+                kind: Kind::Search {
+                    mode: SearchMode::Analyze | SearchMode::GenmoveAnalyze,
+                    ..
+                },
+                ..
+            })
+        )
+    }
+
+    //解析 `B H8 W J9` 形式的棋子，坐标已经有棋子时返回 `None`
+    fn parse_stones(&self, args: &[&str]) -> Option<Vec<(u32, u32, Player)>> {
+        if !args.len().is_multiple_of(2) {
+            return None;
+        }
+        let (w, h) = self.size;
+        let mut stones = vec![];
+        for pair in args.chunks(2) {
+            let player = Player::from_gtp(pair[0])?;
+            match get_move(pair[1], w, h)? {
+                Move::Place(x, y) => {
+                    let occupied = self.stones.iter().chain(&stones);
+                    if occupied.clone().any(|&(sx, sy, _)| (sx, sy) == (x, y)) {
+                        return None;
+                    }
+                    stones.push((x, y, player));
+                }
+                Move::Pass => {}
+                Move::Resign => return None,
+            }
+        }
+        Some(stones)
+    }
+}
+
+/// 解析 Piskvork 的坐标 `x,y`，原点在左上角。
+///
+/// ```
+/// use rboard::engine::piskvork::parse_point;
+///
+/// assert_eq!(parse_point("7,7"), Some((7, 7)));
+/// assert_eq!(parse_point(" 3 , 12 "), Some((3, 12)));
+/// assert_eq!(parse_point("OK"), None);
+/// ```
+pub fn parse_point(s: &str) -> Option<(u32, u32)> {
+    let (x, y) = s.trim().split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+//kata-set-rules 的规则对应 INFO rule 的值
+fn rule_code(rules: &str) -> Option<u32> {
+    match rules.to_ascii_lowercase().as_str() {
+        "freestyle" => Some(0),
+        "standard" => Some(1),
+        "renju" => Some(4),
+        "caro" => Some(8),
+        _ => None,
+    }
+}
+
+//从 ABOUT 的回复 `name="Rapfi", version="1.0"` 中取出一项，没有时返回整行
+fn about_value(line: &str, key: &str) -> String {
+    line.split(',')
+        .filter_map(|item| item.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case(key))
+        .map(|(_, v)| v.trim().trim_matches('"').to_string())
+        .unwrap_or_else(|| line.to_string())
+}
+
+/// 引擎 `MESSAGE` 输出的实时搜索信息，翻译成 `kata-analyze` 的 `info` 行。
+///
+/// 支持两种格式：
+/// - Yixin-Board 的 `REALTIME BEST x,y`；
+/// - Rapfi 等引擎的搜索信息，例如 `depth 12-20 | ev 35 | n 1.2M | pv h8 i9 j10`，
+///   其中 `ev` 为估值（也支持 `wr 55.3%` 胜率），`pv` 的坐标为 `x,y`
+///   或者字母加数字（`a1` 为左上角）。
+#[derive(Default)]
+struct Realtime {
+    winrate: Option<f64>,
+    visits: u64,
+    pv: Vec<(u32, u32)>,
+}
+
+impl Realtime {
+    //返回是否有变化
+    fn update(&mut self, message: &str, size: (u32, u32)) -> bool {
+        let tokens = message
+            .split(|c: char| c.is_whitespace() || c == '|' || c == '=')
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>();
+        if tokens
+            .first()
+            .is_some_and(|t| t.eq_ignore_ascii_case("REALTIME"))
+        {
+            if tokens
+                .get(1)
+                .is_some_and(|t| t.eq_ignore_ascii_case("BEST"))
+                && let Some(point) = tokens.get(2).and_then(|t| parse_point(t))
+            {
+                self.pv = vec![point];
+                return true;
+            }
+            return false;
+        }
+        let mut changed = false;
+        let (mut winrate, mut eval_winrate) = (None, None);
+        let mut i = 0;
+        while i < tokens.len() {
+            let key = tokens[i].trim_end_matches(':').to_ascii_lowercase();
+            let value = tokens.get(i + 1).copied().unwrap_or_default();
+            i += 1;
+            match key.as_str() {
+                "ev" | "eval" => eval_winrate = eval_winrate.or(winrate_from_eval(value)),
+                "wr" | "winrate" => winrate = winrate.or(parse_winrate(value)),
+                "n" | "nodes" | "node" => {
+                    if let Some(visits) = parse_count(value) {
+                        self.visits = visits;
+                        changed = true;
+                    }
+                }
+                "pv" => {
+                    let pv = tokens[i..]
+                        .iter()
+                        .map_while(|t| parse_pv_move(t, size))
+                        .collect::<Vec<_>>();
+                    i += pv.len();
+                    if !pv.is_empty() {
+                        self.pv = pv;
+                        changed = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(winrate) = winrate.or(eval_winrate) {
+            self.winrate = Some(winrate);
+            changed = true;
+        }
+        changed
+    }
+
+    fn info(&self, size_y: u32) -> Option<String> {
+        let coord = |&(x, y): &(u32, u32)| get_coord(x as i32, y as i32, size_y);
+        let best = self.pv.first()?;
+        let pv = self.pv.iter().map(coord).collect::<Vec<_>>().join(" ");
+        Some(format!(
+            "info move {} visits {} winrate {:.4} order 0 pv {}",
+            coord(best),
+            self.visits,
+            self.winrate.unwrap_or(0.5),
+            pv
+        ))
+    }
+}
+
+//估值换算为胜率，`+M15`/`-M15` 为必胜/必败
+fn winrate_from_eval(s: &str) -> Option<f64> {
+    let s = s.trim_start_matches('+');
+    if s.starts_with(['M', 'm']) {
+        Some(1.0)
+    } else if s.starts_with("-M") || s.starts_with("-m") {
+        Some(0.0)
+    } else {
+        let eval = s.parse::<f64>().ok()?;
+        Some(1.0 / (1.0 + (-eval / 200.0).exp()))
+    }
+}
+
+//胜率可以是 0～1、0～100 或者带百分号
+fn parse_winrate(s: &str) -> Option<f64> {
+    let value = s.trim_end_matches('%').parse::<f64>().ok()?;
+    Some(if s.ends_with('%') || value > 1.0 {
+        value / 100.0
+    } else {
+        value
+    })
+}
+
+//节点数，支持 K、M、G 后缀
+fn parse_count(s: &str) -> Option<u64> {
+    let (number, scale) = match s.chars().last()?.to_ascii_uppercase() {
+        'K' => (&s[..s.len() - 1], 1e3),
+        'M' => (&s[..s.len() - 1], 1e6),
+        'G' => (&s[..s.len() - 1], 1e9),
+        _ => (s, 1.0),
+    };
+    Some((number.parse::<f64>().ok()? * scale) as u64)
+}
+
+//pv 中的一手，`x,y` 或者字母加数字（`a1` 为左上角）
+fn parse_pv_move(s: &str, (w, h): (u32, u32)) -> Option<(u32, u32)> {
+    let (x, y) = parse_point(s).or_else(|| {
+        let mut chars = s.chars();
+        let letter = chars.next()?.to_ascii_lowercase();
+        if !letter.is_ascii_lowercase() {
+            return None;
+        }
+        let row = chars.as_str().parse::<u32>().ok()?;
+        Some((letter as u32 - 'a' as u32, row.checked_sub(1)?))
+    })?;
+    (x < w && y < h).then_some((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //模拟 send_command：翻译命令，需要等待回复时加入队列
+    fn send(state: &mut State, command: &str) -> Vec<String> {
+        match state.translate(command) {
+            Action::Remote(lines, kind) => {
+                let (tx, _) = oneshot::channel();
+                state.pending.push_back(Pending {
+                    id: 1,
+                    command: command.to_string(),
+                    kind,
+                    tx,
+                });
+                lines
+            }
+            Action::Local(lines, _) => lines,
+        }
+    }
+
+    #[test]
+    fn analysis_restarts_until_stopped() {
+        let mut state = State::default();
+        send(&mut state, "play B H8");
+        let lines = send(&mut state, "kata-analyze B 50");
+        assert_eq!(lines[0], format!("INFO timeout_turn {}", ANALYZE_TIME));
+        //一次搜索结束，重新搜索同一局面，不回复
+        assert!(state.receive("8,8").is_empty());
+        assert_eq!(state.outbox.drain(..).collect::<Vec<_>>(), lines);
+        assert_eq!(send(&mut state, "stop"), vec!["YXSTOP".to_string()]);
+        let outputs = state.receive("8,8");
+        assert!(matches!(&outputs[..], [EngineOutput::Response(r)] if r.success));
+        assert!(state.outbox.is_empty());
+        assert!(state.pending.is_empty());
+    }
+
+    #[test]
+    fn analysis_ends_when_another_command_waits() {
+        let mut state = State::default();
+        send(&mut state, "kata-analyze B 50");
+        let lines = send(&mut state, "genmove W");
+        assert_eq!(lines[0], "YXSTOP");
+        //分析的回复，然后是引擎的一手
+        assert!(
+            matches!(&state.receive("7,7")[..], [EngineOutput::Response(r)] if r.body.is_empty())
+        );
+        assert!(state.outbox.is_empty());
+        assert!(matches!(&state.receive("7,7")[..], [EngineOutput::Response(r)] if r.body == "H8"));
+    }
+}
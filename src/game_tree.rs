@@ -0,0 +1,453 @@
+use iced::{
+    Element, Length,
+    widget::{Column, Row, Space, button, scrollable, text},
+};
+
+use crate::{
+    chessboard::chessboard_trait::{ChessboardTrait, Player},
+    message::Message,
+};
+
+/// 棋谱树的一个节点。
+#[derive(Clone)]
+pub struct Node {
+    /// 该节点的落子，根节点和停一手为 `None`
+    pub mv: Option<(i32, i32)>,
+    /// 落子方，根节点为 `None`
+    pub player: Option<Player>,
+    /// `player` 在此认输，此时 `mv` 为 `None`
+    pub resign: bool,
+    pub comment: String,
+    /// 终局点目时标记的死子，写入 SGF 的 `MA`
+    pub dead: Vec<(u32, u32)>,
+    pub parent: Option<usize>,
+    /// 子节点，`children[0]` 为主变化
+    pub children: Vec<usize>,
+    /// 最近一次走过的子节点，用于“下一步”回到原来的变化
+    active_child: usize,
+}
+
+impl Node {
+    fn new(mv: Option<(i32, i32)>, player: Option<Player>, parent: Option<usize>) -> Self {
+        Node {
+            mv,
+            player,
+            resign: false,
+            comment: String::new(),
+            dead: Vec::new(),
+            parent,
+            children: Vec::new(),
+            active_child: 0,
+        }
+    }
+}
+
+/// 对局信息，对应 SGF 根节点中的属性。
+#[derive(Default, Clone)]
+pub struct GameInfo {
+    /// 黑方名字 `PB`
+    pub black: String,
+    /// 白方名字 `PW`
+    pub white: String,
+    /// 载入的棋谱中的对局结果 `RE`，例如 `"B+R"`。保存时由当前节点的结果决定，见 `BoardState::result_text`
+    pub result: String,
+    /// 贴目 `KM`
+    pub komi: Option<f64>,
+    /// 摆棋摆出的初始局面 `AB`/`AW`，为 `(x, y, 棋子)`
+    pub setup: Vec<(u32, u32, Player)>,
+    /// 摆棋后先走的一方 `PL`，`None` 时由棋盘决定
+    pub to_play: Option<Player>,
+}
+
+/// 带分支的棋谱，节点保存在数组中，通过下标互相引用。
+#[derive(Clone)]
+pub struct GameTree {
+    pub info: GameInfo,
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+impl GameTree {
+    pub fn new() -> Self {
+        GameTree {
+            info: GameInfo::default(),
+            nodes: vec![Node::new(None, None, None)],
+            current: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn root(&self) -> usize {
+        0
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn node(&self, index: usize) -> &Node {
+        &self.nodes[index]
+    }
+
+    pub fn current_node(&self) -> &Node {
+        &self.nodes[self.current]
+    }
+
+    /// 当前节点的深度，即第几手。
+    pub fn depth(&self, index: usize) -> usize {
+        let mut depth = 0;
+        let mut node = index;
+        while let Some(parent) = self.nodes[node].parent {
+            depth += 1;
+            node = parent;
+        }
+        depth
+    }
+
+    /// 从根节点到当前节点的全部落子，`None` 为停一手，认输不算在内。
+    pub fn path(&self) -> Vec<Option<(i32, i32)>> {
+        let mut moves = Vec::new();
+        let mut node = self.current;
+        while let Some(parent) = self.nodes[node].parent {
+            if !self.nodes[node].resign {
+                moves.push(self.nodes[node].mv);
+            }
+            node = parent;
+        }
+        moves.reverse();
+        moves
+    }
+
+    /// 当前节点所在的整条变化：从根节点经过当前节点，沿最近走过的分支一直到末尾。
+    pub fn active_line(&self) -> Vec<Option<(i32, i32)>> {
+        let mut moves = self.path();
+        let mut node = &self.nodes[self.current];
+        while let Some(&child) = node.children.get(node.active_child) {
+            node = &self.nodes[child];
+            if !node.resign {
+                moves.push(node.mv);
+            }
+        }
+        moves
+    }
+
+    /// 在当前节点后落子。已有相同落子的子节点时直接进入该节点，否则新建一个变化。
+    pub fn play(&mut self, player: Player, x: i32, y: i32) -> usize {
+        self.add(player, Some((x, y)))
+    }
+
+    /// 在当前节点后停一手。
+    pub fn pass(&mut self, player: Player) -> usize {
+        self.add(player, None)
+    }
+
+    /// 在当前节点后认输。
+    pub fn resign(&mut self, player: Player) -> usize {
+        self.add_node(player, None, true)
+    }
+
+    fn add(&mut self, player: Player, mv: Option<(i32, i32)>) -> usize {
+        self.add_node(player, mv, false)
+    }
+
+    fn add_node(&mut self, player: Player, mv: Option<(i32, i32)>, resign: bool) -> usize {
+        let child = self.nodes[self.current]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| {
+                let node = &self.nodes[c];
+                node.mv == mv && node.player == Some(player) && node.resign == resign
+            });
+        let child = match child {
+            Some(child) => child,
+            None => {
+                let index = self.nodes.len();
+                let mut node = Node::new(mv, Some(player), Some(self.current));
+                node.resign = resign;
+                self.nodes.push(node);
+                self.nodes[self.current].children.push(index);
+                index
+            }
+        };
+        self.select(child);
+        child
+    }
+
+    /// 选中任意节点，并把沿途的父节点的活动分支指向它。
+    pub fn select(&mut self, index: usize) {
+        if index >= self.nodes.len() {
+            return;
+        }
+        let mut node = index;
+        while let Some(parent) = self.nodes[node].parent {
+            if let Some(i) = self.nodes[parent].children.iter().position(|&c| c == node) {
+                self.nodes[parent].active_child = i;
+            }
+            node = parent;
+        }
+        self.current = index;
+    }
+
+    pub fn back(&mut self) -> bool {
+        match self.nodes[self.current].parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn forward(&mut self) -> bool {
+        let node = &self.nodes[self.current];
+        match node.children.get(node.active_child) {
+            Some(&child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn first(&mut self) -> bool {
+        let moved = self.current != self.root();
+        self.current = self.root();
+        moved
+    }
+
+    pub fn last(&mut self) -> bool {
+        let mut moved = false;
+        while self.forward() {
+            moved = true;
+        }
+        moved
+    }
+
+    /// 切换到当前节点的兄弟变化，`delta` 为偏移量（-1 上一个变化，1 下一个变化）。
+    pub fn switch_branch(&mut self, delta: i32) -> bool {
+        let Some(parent) = self.nodes[self.current].parent else {
+            return false;
+        };
+        let siblings = &self.nodes[parent].children;
+        let Some(i) = siblings.iter().position(|&c| c == self.current) else {
+            return false;
+        };
+        let j = i as i32 + delta;
+        if j < 0 || j >= siblings.len() as i32 {
+            return false;
+        }
+        let sibling = siblings[j as usize];
+        self.select(sibling);
+        true
+    }
+
+    pub fn set_comment(&mut self, comment: String) {
+        self.nodes[self.current].comment = comment;
+    }
+
+    pub fn set_dead(&mut self, dead: Vec<(u32, u32)>) {
+        self.nodes[self.current].dead = dead;
+    }
+
+    /// 按变化把棋谱拆成多行，用于绘制树形控件。
+    ///
+    /// # 返回值
+    /// 每个元素为 `(起始深度, 节点列表)`，第一行为主变化，
+    /// 之后每个分支单独一行，按先序排列。
+    pub fn lines(&self) -> Vec<(usize, Vec<usize>)> {
+        let mut lines = Vec::new();
+        let mut starts = vec![self.root()];
+        while let Some(start) = starts.pop() {
+            let depth = self.depth(start);
+            let mut line = vec![];
+            let mut branches = vec![];
+            let mut node = start;
+            loop {
+                line.push(node);
+                let children = &self.nodes[node].children;
+                if children.is_empty() {
+                    break;
+                }
+                branches.extend(children[1..].iter().copied());
+                node = children[0];
+            }
+            lines.push((depth, line));
+            //倒序入栈，保证靠前的分支先输出
+            starts.extend(branches.into_iter().rev());
+        }
+        lines
+    }
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 棋谱树控件：每个变化一行，按深度对齐，当前节点高亮。坐标由 `chessboard` 决定。
+pub fn view<'a>(tree: &GameTree, chessboard: &dyn ChessboardTrait) -> Element<'a, Message> {
+    let cell = 36.0;
+    let mut lines = Column::new().spacing(2.0);
+    for (depth, nodes) in tree.lines() {
+        let mut line = Row::new()
+            .spacing(0.0)
+            .push(Space::with_width(cell * depth as f32));
+        for index in nodes {
+            let node = tree.node(index);
+            let label = match (node.mv, node.player) {
+                _ if node.resign => "认输".to_string(),
+                (Some((x, y)), _) => chessboard.get_coord(x, y),
+                (None, Some(_)) => "pass".to_string(),
+                (None, None) => "0".to_string(),
+            };
+            let style = if index == tree.current() {
+                button::primary
+            } else {
+                button::secondary
+            };
+            line = line.push(
+                button(text(label).size(11.0))
+                    .padding(2.0)
+                    .width(cell)
+                    .style(style)
+                    .on_press(Message::SelectNode(index)),
+            );
+        }
+        lines = lines.push(line);
+    }
+    scrollable(lines)
+        .direction(scrollable::Direction::Both {
+            vertical: scrollable::Scrollbar::default(),
+            horizontal: scrollable::Scrollbar::default(),
+        })
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //0 - B(1,1) - W(2,2) - B(3,3)
+    //          \- W(4,4) - B(5,5)
+    fn branched() -> GameTree {
+        let mut tree = GameTree::new();
+        tree.play(Player::Black, 1, 1);
+        tree.play(Player::White, 2, 2);
+        tree.play(Player::Black, 3, 3);
+        tree.select(1);
+        tree.play(Player::White, 4, 4);
+        tree.play(Player::Black, 5, 5);
+        tree
+    }
+
+    #[test]
+    fn same_move_reuses_the_branch() {
+        let mut tree = branched();
+        tree.select(1);
+        assert_eq!(tree.play(Player::White, 2, 2), 2);
+        assert_eq!(tree.play(Player::Black, 3, 3), 3);
+        assert_eq!(tree.nodes.len(), 6);
+        assert_eq!(tree.node(1).children, vec![2, 4]);
+        //落子方不同或认输时新建变化
+        tree.select(1);
+        assert_eq!(tree.play(Player::Black, 2, 2), 6);
+        tree.select(1);
+        assert_eq!(tree.pass(Player::White), 7);
+        tree.select(1);
+        assert_eq!(tree.pass(Player::White), 7);
+        tree.select(1);
+        assert_eq!(tree.resign(Player::White), 8);
+        assert_eq!(tree.node(1).children, vec![2, 4, 6, 7, 8]);
+    }
+
+    #[test]
+    fn select_and_switch_branch_track_the_active_line() {
+        let mut tree = branched();
+        assert_eq!(tree.current(), 5);
+        assert_eq!(
+            tree.active_line(),
+            vec![Some((1, 1)), Some((4, 4)), Some((5, 5))]
+        );
+        //回到根节点后“下一步”沿最近走过的变化
+        tree.first();
+        tree.last();
+        assert_eq!(tree.current(), 5);
+
+        tree.select(3);
+        assert_eq!(tree.path(), vec![Some((1, 1)), Some((2, 2)), Some((3, 3))]);
+        tree.select(1);
+        assert_eq!(
+            tree.active_line(),
+            vec![Some((1, 1)), Some((2, 2)), Some((3, 3))]
+        );
+
+        tree.select(2);
+        assert!(tree.switch_branch(1));
+        assert_eq!(tree.current(), 4);
+        assert_eq!(
+            tree.active_line(),
+            vec![Some((1, 1)), Some((4, 4)), Some((5, 5))]
+        );
+        assert!(!tree.switch_branch(1));
+        assert!(tree.switch_branch(-1));
+        assert_eq!(tree.current(), 2);
+        assert!(!tree.switch_branch(-1));
+        tree.first();
+        assert!(!tree.switch_branch(1));
+        tree.last();
+        assert_eq!(tree.current(), 3);
+    }
+
+    #[test]
+    fn resign_is_not_a_move() {
+        let mut tree = branched();
+        tree.select(3);
+        let resign = tree.resign(Player::White);
+        assert!(tree.node(resign).resign);
+        assert_eq!(tree.node(resign).mv, None);
+        assert_eq!(tree.path(), vec![Some((1, 1)), Some((2, 2)), Some((3, 3))]);
+        assert_eq!(tree.depth(resign), 4);
+        tree.select(1);
+        assert_eq!(
+            tree.active_line(),
+            vec![Some((1, 1)), Some((2, 2)), Some((3, 3))]
+        );
+        //停一手仍然算作一手
+        tree.select(5);
+        tree.pass(Player::White);
+        assert_eq!(
+            tree.path(),
+            vec![Some((1, 1)), Some((4, 4)), Some((5, 5)), None]
+        );
+    }
+
+    #[test]
+    fn lines_are_laid_out_in_pre_order() {
+        let mut tree = branched();
+        assert_eq!(tree.lines(), vec![(0, vec![0, 1, 2, 3]), (2, vec![4, 5])]);
+        tree.select(0);
+        tree.play(Player::Black, 6, 6);
+        tree.select(4);
+        tree.play(Player::Black, 7, 7);
+        tree.play(Player::White, 8, 8);
+        //靠近根节点的分支在前，分支内部的分支紧跟在该分支之后
+        assert_eq!(
+            tree.lines(),
+            vec![
+                (0, vec![0, 1, 2, 3]),
+                (1, vec![6]),
+                (2, vec![4, 5]),
+                (3, vec![7, 8]),
+            ]
+        );
+        tree.clear();
+        assert_eq!(tree.lines(), vec![(0, vec![0])]);
+    }
+}
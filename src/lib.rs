@@ -1,29 +1,43 @@
 pub mod board;
 pub mod chessboard;
 pub mod engine;
+pub mod game_tree;
 pub mod message;
+pub mod opening;
+pub mod psq;
+pub mod renlib;
+pub mod sgf;
 pub mod style;
 
-use std::sync::{Arc, Mutex};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
 
 use iced::futures::{self, SinkExt, Stream, StreamExt};
 use iced::widget::{
-    Column, button, canvas, column, progress_bar, responsive, row, text, text_editor, text_input,
+    Column, Row, button, canvas, column, container, pick_list, progress_bar, responsive, row,
+    scrollable, text, text_editor, text_input,
 };
-use iced::{Background, Border, Color, Font, Length, Subscription, Task};
+use iced::{Background, Border, Color, Font, Length, Subscription, Task, keyboard};
 use iced_aw::menu::{Item, Menu};
 use iced_aw::{SelectionList, menu_bar, menu_items, selection_list};
 use iced_table::table;
 use rfd::AsyncFileDialog;
 
 use crate::board::{Board, BoardState};
-use crate::chessboard::chessboard_trait::Player;
-use crate::chessboard::get_all_board_names;
+use crate::chessboard::chessboard_trait::{GameResult, Player};
+use crate::chessboard::{MAX_BOARD_SIZE, MIN_BOARD_SIZE, Move, get_all_board_names};
 use crate::engine::analyze::Analyzes;
 use crate::engine::analyzes_table::AnalyzesTable;
+use crate::engine::capabilities::{AnalyzeCommand, Capabilities};
+use crate::engine::engine_args::{EngineArgs, Protocol};
 use crate::engine::engine_paths::EnginePaths;
-use crate::engine::gtp::GTP;
+use crate::engine::gtp::{EngineOutput, Response};
+use crate::engine::match_runner::{GameRecord, MatchConfig, MatchScore, parse_openings, run_match};
+use crate::engine::{Engine, start_engine};
 use crate::message::Message;
+use crate::opening::{Opening, OpeningProtocol};
 
 use crate::style as styles;
 
@@ -37,18 +51,52 @@ pub fn start() -> iced::Result {
 
 struct RBoard {
     board_state: BoardState,
+    //开局规则，`None` 为自由开局
+    opening: Option<Opening>,
     engine_path: EnginePaths,
 
+    show_size_dialog: bool,
+    //摆棋模式
+    edit_mode: bool,
+    //进入摆棋前确认清除棋谱
+    show_edit_confirm: bool,
+    board_width: String,
+    board_height: String,
+
     show_engine_manager: bool,
+
+    show_match_dialog: bool,
+    //对战双方在 engine_path 中的下标
+    match_engines: [Option<usize>; 2],
+    match_games: String,
+    match_openings: Vec<Vec<String>>,
+    //正在对战时为停止标志
+    match_stop: Option<Arc<AtomicBool>>,
+    match_records: Vec<GameRecord>,
+    match_score: MatchScore,
+    match_status: String,
+
     engine_name_list: Vec<String>,
     engine_setting_selected: Option<usize>,
     engine_setting_arg_content: text_editor::Content,
 
-    engine: Option<GTP>,
+    engine: Option<Box<dyn Engine>>,
+    //引擎 list_commands 的回复，收到之前为 `None`，此时不同步局面也不分析
+    engine_capabilities: Option<Capabilities>,
+    //人机对弈时引擎执的颜色，`None` 为只分析
+    engine_color: Option<Player>,
+    //正在运行的分析命令，决定 info 行的格式
+    engine_analyzing: Option<AnalyzeCommand>,
+    //正在等待回复的 genmove 命令的编号，局面重置后之前的回复不再匹配
+    genmove_id: Option<u32>,
+    //引擎对 name、version、kata-get-rules 的回复
+    engine_name: Option<String>,
+    engine_version: Option<String>,
+    engine_rules: Option<String>,
     engine_msg: Vec<String>,
     engine_analyze: String,
 
-    engine_tx: Arc<Mutex<iced::futures::channel::mpsc::Sender<String>>>,
+    engine_tx: Arc<Mutex<iced::futures::channel::mpsc::Sender<EngineOutput>>>,
 
     engine_analyzes_table: AnalyzesTable,
     analyzes: Arc<Analyzes>,
@@ -57,16 +105,37 @@ struct RBoard {
 
 impl Default for RBoard {
     fn default() -> Self {
-        let (tx, _) = futures::channel::mpsc::channel::<String>(100);
+        let (tx, _) = futures::channel::mpsc::channel::<EngineOutput>(100);
 
         Self {
             board_state: Default::default(),
+            opening: None,
             engine_path: Default::default(),
+            show_size_dialog: false,
+            edit_mode: false,
+            show_edit_confirm: false,
+            board_width: String::new(),
+            board_height: String::new(),
             show_engine_manager: false,
+            show_match_dialog: false,
+            match_engines: [None, None],
+            match_games: "10".to_string(),
+            match_openings: vec![],
+            match_stop: None,
+            match_records: vec![],
+            match_score: MatchScore::default(),
+            match_status: String::new(),
             engine_name_list: Vec::new(),
             engine_setting_selected: None,
             engine_setting_arg_content: Default::default(),
             engine: None,
+            engine_capabilities: None,
+            engine_color: None,
+            engine_analyzing: None,
+            genmove_id: None,
+            engine_name: None,
+            engine_version: None,
+            engine_rules: None,
             engine_msg: Vec::new(),
             engine_analyze: String::new(),
             engine_tx: Arc::new(Mutex::new(tx)),
@@ -79,37 +148,288 @@ impl Default for RBoard {
 
 impl RBoard {
     fn update(&mut self, message: Message) -> iced::Task<Message> {
+        //摆棋时、轮到引擎或者引擎思考时不能落子
+        let engine_busy =
+            self.genmove_id.is_some() || (self.engine.is_some() && self.is_engine_turn());
+        if (self.edit_mode || engine_busy)
+            && matches!(
+                message,
+                Message::GoBoard(..) | Message::Pass | Message::Resign
+            )
+        {
+            return Task::none();
+        }
+        //引擎思考时不能改变局面，否则引擎的回复会落在别的局面上
+        if self.genmove_id.is_some()
+            && matches!(
+                message,
+                Message::Undo
+                    | Message::Redo
+                    | Message::FirstMove
+                    | Message::LastMove
+                    | Message::SwitchBranch(_)
+                    | Message::SelectNode(_)
+                    | Message::ToggleEditMode
+                    | Message::ConfirmEditMode
+            )
+        {
+            return Task::none();
+        }
+        //摆候选点和挑选第五手时悔棋只在开局规则之内退一步
+        if matches!(message, Message::Undo)
+            && self.opening.as_mut().is_some_and(|opening| opening.undo())
+        {
+            return Task::none();
+        }
+        //开局状态只随落子前进，悔棋或跳转之后不再对应棋盘上的局面，退出开局规则
+        if self.opening.is_some()
+            && matches!(
+                message,
+                Message::Undo
+                    | Message::Redo
+                    | Message::FirstMove
+                    | Message::LastMove
+                    | Message::SwitchBranch(_)
+                    | Message::SelectNode(_)
+            )
+        {
+            self.opening = None;
+        }
         match message {
             Message::GoBoard(x, y) => {
-                if let Some(cmd) = self.board_state.chessboard.go(x, y) {
-                    if let Some(gtp) = self.engine.take() {
-                        println!("cmd: {}", cmd);
-                        let _ = gtp.send_command(cmd);
-                        let _ = gtp.send_kata_analyze();
-                        self.engine = Some(gtp);
+                //终局后点击棋子切换死活，不算一手棋
+                if self.board_state.toggle_dead(x, y) {
+                    return Task::none();
+                }
+                let (x, y) = match &mut self.opening {
+                    Some(opening) => {
+                        let pieces = self.board_state.chessboard.get_pieces();
+                        if pieces[x as usize][y as usize].is_some() {
+                            return Task::none();
+                        }
+                        match opening.click(x, y) {
+                            Some(p) => p,
+                            None => return Task::none(),
+                        }
+                    }
+                    None => (x, y),
+                };
+                if let Some(cmds) = self.board_state.go(x, y) {
+                    if let Some(gtp) = &self.engine {
+                        for cmd in cmds {
+                            println!("cmd: {}", cmd);
+                            let _ = gtp.send_command(cmd);
+                        }
+                    }
+                    //开局可能在这一手结束，之后才轮到引擎
+                    if let Some(opening) = &mut self.opening {
+                        let tree = &self.board_state.tree;
+                        opening.after_move(tree.depth(tree.current()));
                     }
+                    self.resume_analyze();
                 }
             }
-            Message::NewBoard => {
-                self.board_state.chessboard.new_board();
-                if let Some(gtp) = self.engine.take() {
-                    let _ = gtp.send_command("stop".to_string());
-                    let _ = gtp.send_command("clear_board".to_string());
-                    let _ = gtp.send_kata_analyze();
-                    self.engine = Some(gtp);
-                    self.analyzes = Arc::new(Default::default());
-                    self.engine_analyzes_table.rows = vec![];
-                    self.engine_msg.clear();
+            Message::Pass => {
+                if let Some(cmds) = self.board_state.pass() {
+                    if let Some(gtp) = &self.engine {
+                        for cmd in cmds {
+                            let _ = gtp.send_command(cmd);
+                        }
+                    }
+                    self.resume_analyze();
                 }
             }
+            Message::Resign => {
+                let resigned = self.board_state.resign();
+                if resigned {
+                    self.resume_analyze();
+                }
+            }
+            Message::StartOpening(protocol) => {
+                if let Some(protocol) = protocol {
+                    self.new_board(Some(Opening::new(protocol)));
+                } else {
+                    self.opening = None;
+                    self.resume_analyze();
+                }
+            }
+            Message::OpeningChoose(choice) => {
+                if let Some(opening) = &mut self.opening {
+                    //人机对弈时引擎坐在选择之前执 engine_color 的一方，选择之后改执这一方的颜色
+                    let seat = self.engine_color.map(|color| opening.seat(color));
+                    opening.choose(choice);
+                    self.engine_color = seat.map(|seat| opening.color(seat));
+                    let (black, white) = opening.player_names();
+                    self.board_state.tree.info.black = black;
+                    self.board_state.tree.info.white = white;
+                    self.resume_analyze();
+                }
+            }
+            Message::Undo => {
+                //人机对弈时一直悔到轮到玩家，否则引擎马上又会落子
+                let mut undos = 0;
+                let mut undone = None;
+                loop {
+                    //认输没有发送给引擎，撤销时不需要 undo
+                    let resigned = self.board_state.tree.current_node().resign;
+                    let Some(cmds) = self.board_state.undo() else {
+                        break;
+                    };
+                    if !resigned {
+                        undos += 1;
+                    }
+                    undone = Some(cmds);
+                    if !self.is_engine_turn() {
+                        break;
+                    }
+                }
+                if let Some(cmds) = undone {
+                    if undos == 0 {
+                        self.resume_analyze();
+                    } else if self.supports(|c| c.undo) {
+                        if let Some(gtp) = &self.engine {
+                            for _ in 0..undos {
+                                let _ = gtp.send_command("undo".to_string());
+                            }
+                        }
+                        self.analyzes = Arc::new(Default::default());
+                        self.engine_analyzes_table.rows = vec![];
+                        self.resume_analyze();
+                    } else {
+                        self.sync_engine(cmds);
+                    }
+                }
+            }
+            Message::Redo => {
+                if let Some(cmd) = self.board_state.redo()
+                    && let Some(gtp) = &self.engine
+                {
+                    let _ = gtp.send_command(cmd);
+                }
+                //前进到认输时没有落子命令，但需要停止分析
+                self.resume_analyze();
+            }
+            Message::FirstMove => {
+                if let Some(cmds) = self.board_state.first() {
+                    self.sync_engine(cmds);
+                }
+            }
+            Message::LastMove => {
+                if let Some(cmds) = self.board_state.last() {
+                    self.sync_engine(cmds);
+                }
+            }
+            Message::SwitchBranch(delta) => {
+                if let Some(cmds) = self.board_state.switch_branch(delta) {
+                    self.sync_engine(cmds);
+                }
+            }
+            Message::SelectNode(index) => {
+                if let Some(cmds) = self.board_state.select(index) {
+                    self.sync_engine(cmds);
+                }
+            }
+            Message::ChangeComment(comment) => self.board_state.tree.set_comment(comment),
+            Message::ToggleEditMode => {
+                if self.edit_mode {
+                    //摆完之后把局面同步给引擎，再继续分析
+                    self.edit_mode = false;
+                    let cmds = self.board_state.replay();
+                    self.sync_engine(cmds);
+                } else if self.board_state.has_moves() {
+                    //摆棋会清除棋谱中的全部棋步，先请玩家确认
+                    self.show_edit_confirm = true;
+                } else {
+                    self.start_edit();
+                }
+            }
+            Message::ConfirmEditMode => {
+                self.show_edit_confirm = false;
+                self.start_edit();
+            }
+            Message::CloseEditConfirm => self.show_edit_confirm = false,
+            Message::EditBoard(x, y, player) => {
+                self.board_state.edit(x, y, player);
+            }
+            Message::SetToPlay(player) => self.board_state.set_to_play(player),
+            Message::SetEngineColor(color) => {
+                self.engine_color = color;
+                if self.genmove_id.is_some() {
+                    //引擎已经记下了这一手，丢弃回复之后重新同步局面
+                    self.cancel_genmove();
+                    let cmds = self.board_state.replay();
+                    self.sync_engine(cmds);
+                } else {
+                    self.resume_analyze();
+                }
+            }
+            Message::NewBoard => self.new_board(None),
             Message::ChangeBoard(name) => {
-                self.board_state.change_board(name);
-                if let Some(mut gtp) = self.engine.take() {
+                self.board_state.change_board(name, None);
+                self.opening = None;
+                self.edit_mode = false;
+                self.close_engine();
+            }
+            Message::OpenSizeDialog => {
+                let (x, y) = self.board_state.chessboard.get_length();
+                self.board_width = x.to_string();
+                self.board_height = y.to_string();
+                self.show_size_dialog = true;
+            }
+            Message::CloseSizeDialog => self.show_size_dialog = false,
+            Message::ChangeBoardWidth(width) => self.board_width = width,
+            Message::ChangeBoardHeight(height) => self.board_height = height,
+            Message::ConfirmBoardSize => {
+                let valid = MIN_BOARD_SIZE..=MAX_BOARD_SIZE;
+                let (Ok(x), Ok(y)) = (self.board_width.parse(), self.board_height.parse()) else {
+                    return Task::none();
+                };
+                if !valid.contains(&x) || !valid.contains(&y) {
+                    return Task::none();
+                }
+                self.show_size_dialog = false;
+                let name = self.board_state.name.clone();
+                self.board_state.change_board(name, Some((x, y)));
+                self.opening = None;
+                self.edit_mode = false;
+                self.cancel_genmove();
+                if let Some(gtp) = &self.engine
+                    && self.engine_capabilities.is_some()
+                {
                     let _ = gtp.send_command("stop".to_string());
-                    let _ = gtp.exit();
-                    self.analyzes = Arc::new(Default::default());
-                    self.engine_analyzes_table.rows = vec![];
-                    self.engine_msg.clear();
+                    let _ = gtp.send_boardsize((x, y));
+                    self.check_board_size();
+                }
+                self.sync_engine(vec![]);
+            }
+            Message::OpenRecordButton => {
+                return Task::perform(
+                    AsyncFileDialog::new()
+                        .add_filter("棋谱", &["sgf", "lib", "psq"])
+                        .set_title("打开棋谱...")
+                        .pick_file(),
+                    Message::OpenRecord,
+                );
+            }
+            Message::OpenRecord(Some(file)) => {
+                let name = self.board_state.name.clone();
+                let loaded = self.board_state.load_file(file.path());
+                self.after_load(name, loaded);
+            }
+            Message::SaveRecordButton => {
+                return Task::perform(
+                    AsyncFileDialog::new()
+                        .add_filter("sgf", &["sgf"])
+                        .add_filter("psq", &["psq"])
+                        .set_title("保存棋谱...")
+                        .set_file_name("game.sgf")
+                        .save_file(),
+                    Message::SaveRecord,
+                );
+            }
+            Message::SaveRecord(Some(file)) => {
+                if let Err(e) = self.board_state.save_file(file.path()) {
+                    eprintln!("save record err: {}", e);
                 }
             }
             Message::AddEngineButton => {
@@ -169,6 +489,9 @@ impl RBoard {
                         .change_args(index, self.engine_setting_arg_content.text());
                 }
             }
+            Message::ChangeEngineProtocol(index, protocol) => {
+                let _ = self.engine_path.change_protocol(index, protocol);
+            }
             Message::DeleteEngine => {
                 if let Some(i) = self.engine_setting_selected {
                     let _ = self.engine_path.delete(i);
@@ -187,21 +510,19 @@ impl RBoard {
                 if let Some(mut engine) = self.engine.take() {
                     let _ = engine.exit();
                 }
+                self.genmove_id = None;
+                self.engine_name = None;
+                self.engine_version = None;
+                self.engine_rules = None;
 
-                let gtp = GTP::start(
-                    &args.path.as_str(),
-                    &args.args.as_str(),
-                    Arc::clone(&self.engine_tx),
-                );
+                let gtp = start_engine(&args, Arc::clone(&self.engine_tx));
                 match gtp {
                     Ok(gtp) => {
+                        //收到 list_commands 的回复之后再设置棋盘，见 init_engine
+                        self.engine_capabilities = None;
                         let _ = gtp.send_command("name".to_string());
                         let _ = gtp.send_command("version".to_string());
                         let _ = gtp.send_command("list_commands".to_string());
-                        let (x, _) = self.board_state.chessboard.get_length();
-                        let _ = gtp.send_command(format!("boardsize {}", x));
-                        let _ = gtp.send_command("kata-get-rules".to_string());
-                        let _ = gtp.send_kata_analyze();
                         self.engine = Some(gtp);
                         self.engine_path.current_path = Some(index as i32);
                     }
@@ -216,10 +537,15 @@ impl RBoard {
                 println!("change sender!");
                 self.engine_tx = Arc::new(Mutex::new(sender));
             }
-            Message::EngineReceiveOutput(data) => {
-                if data.starts_with("info") {
+            Message::EngineReceiveOutput(output) => match output {
+                //分析已经停止时残留的 info 行直接丢弃
+                EngineOutput::Info(_) if self.engine_analyzing.is_none() => {}
+                EngineOutput::Info(data) => {
                     self.engine_analyze = data;
-                    let analyzes = Analyzes::from_string(&self.engine_analyze);
+                    let analyzes = self
+                        .engine_analyzing
+                        .unwrap_or(AnalyzeCommand::Kata)
+                        .parse(&self.engine_analyze);
                     self.engine_analyzes_table.rows = analyzes.datas.clone();
                     self.analyzes = Arc::new(analyzes);
                     if self.analyzes.datas.len() > 0 {
@@ -230,24 +556,92 @@ impl RBoard {
                             self.black_winrate = 100.0 - winrate;
                         }
                     }
-                } else if data.starts_with("Why you give a finished board here") {
-                    if let Some(gtp) = self.engine.take() {
-                        let _ = gtp.send_command("stop".to_string());
-                        self.engine = Some(gtp);
-                        self.analyzes = Arc::new(Default::default());
-                        self.engine_analyzes_table.rows = vec![];
+                }
+                EngineOutput::Response(response) => {
+                    self.engine_msg
+                        .extend(response.to_string().lines().map(String::from));
+                    self.receive_response(response);
+                }
+                EngineOutput::Log(data) => self.engine_msg.push(data),
+            },
+            Message::CloseEngine => self.close_engine(),
+            Message::OpenMatchDialog => {
+                let count = self.engine_path.paths.len();
+                for (slot, engine) in self.match_engines.iter_mut().enumerate() {
+                    if engine.is_none_or(|i| i >= count) {
+                        *engine = (slot < count).then_some(slot);
                     }
-                } else {
-                    self.engine_msg.push(data);
                 }
+                self.show_match_dialog = true;
             }
-            Message::CloseEngine => {
-                if let Some(mut gtp) = self.engine.take() {
-                    let _ = gtp.send_command("stop".to_string());
-                    let _ = gtp.exit();
-                    self.analyzes = Arc::new(Default::default());
-                    self.engine_analyzes_table.rows = vec![];
-                    self.engine_msg.clear();
+            Message::CloseMatchDialog => self.show_match_dialog = false,
+            Message::ChangeMatchEngine(slot, name) => {
+                self.match_engines[slot] =
+                    self.engine_path.paths.iter().position(|e| e.name == name);
+            }
+            Message::ChangeMatchGames(games) => self.match_games = games,
+            Message::OpenMatchOpeningsButton => {
+                return Task::perform(
+                    AsyncFileDialog::new()
+                        .add_filter("开局库", &["txt"])
+                        .set_title("打开开局库...")
+                        .pick_file(),
+                    Message::OpenMatchOpenings,
+                );
+            }
+            Message::OpenMatchOpenings(Some(file)) => match std::fs::read_to_string(file.path()) {
+                Ok(s) => self.match_openings = parse_openings(&s),
+                Err(e) => eprintln!("open openings err: {}", e),
+            },
+            Message::StartMatch => {
+                let paths = self.engine_path.get_all_paths();
+                let [Some(first), Some(second)] = self
+                    .match_engines
+                    .map(|i| i.and_then(|i| paths.get(i).cloned()))
+                else {
+                    return Task::none();
+                };
+                let Ok(games) = self.match_games.parse::<u32>() else {
+                    return Task::none();
+                };
+                if games == 0 || self.match_stop.is_some() {
+                    return Task::none();
+                }
+                let config = MatchConfig {
+                    board: self.board_state.name.clone(),
+                    size: Some(self.board_state.chessboard.get_length()),
+                    games,
+                    openings: self.match_openings.clone(),
+                };
+                let stop = Arc::new(AtomicBool::new(false));
+                self.match_stop = Some(Arc::clone(&stop));
+                self.match_records.clear();
+                self.match_score = MatchScore::default();
+                self.match_status = "对战中...".to_string();
+                return Task::stream(match_events(config, first, second, stop));
+            }
+            Message::StopMatch => {
+                if let Some(stop) = &self.match_stop {
+                    stop.store(true, Ordering::Relaxed);
+                    self.match_status = "正在停止...".to_string();
+                }
+            }
+            Message::MatchGame(record) => {
+                self.match_score.add(&record);
+                self.match_records.push(record);
+            }
+            Message::MatchFinished(result) => {
+                self.match_stop = None;
+                self.match_status = match result {
+                    Ok(()) => "对战结束".to_string(),
+                    Err(e) => e,
+                };
+            }
+            Message::SelectMatchGame(i) => {
+                if let Some(record) = self.match_records.get(i) {
+                    let name = self.board_state.name.clone();
+                    let loaded = self.board_state.load_sgf(&record.sgf);
+                    self.after_load(name, loaded);
                 }
             }
             _ => {}
@@ -281,6 +675,10 @@ impl RBoard {
             "关闭引擎",
             Message::CloseEngine,
         )));
+        engine_path.push(Item::new(styles::button::secondary_menu_button(
+            "引擎对战...",
+            Message::OpenMatchDialog,
+        )));
 
         let mut all_board = vec![];
         for (name, id) in get_all_board_names() {
@@ -289,6 +687,41 @@ impl RBoard {
                 Message::ChangeBoard(id),
             )));
         }
+        all_board.push(Item::new(styles::button::secondary_menu_button(
+            "棋盘大小...",
+            Message::OpenSizeDialog,
+        )));
+
+        let mut all_mode = vec![];
+        //引擎不支持 genmove 时只能分析
+        let can_play = self
+            .engine_capabilities
+            .as_ref()
+            .is_none_or(|c| c.can_genmove());
+        for (name, color) in [
+            ("分析", None),
+            ("人机对弈：引擎执黑", Some(Player::Black)),
+            ("人机对弈：引擎执白", Some(Player::White)),
+        ] {
+            if color.is_some() && !can_play {
+                continue;
+            }
+            all_mode.push(Item::new(styles::button::secondary_menu_button(
+                text(name),
+                Message::SetEngineColor(color),
+            )));
+        }
+
+        let mut all_opening = vec![Item::new(styles::button::secondary_menu_button(
+            "自由开局",
+            Message::StartOpening(None),
+        ))];
+        for (name, protocol) in OpeningProtocol::all() {
+            all_opening.push(Item::new(styles::button::secondary_menu_button(
+                text(name),
+                Message::StartOpening(Some(protocol)),
+            )));
+        }
 
         #[rustfmt::skip]
         let menu_bar = menu_bar!(
@@ -296,6 +729,12 @@ impl RBoard {
             text("菜单"),
             menu_template(menu_items!(
                 (styles::button::secondary_menu_button("新棋盘", Message::NewBoard))
+                (styles::button::secondary_menu_button("打开棋谱...", Message::OpenRecordButton))
+                (styles::button::secondary_menu_button("保存棋谱...", Message::SaveRecordButton))
+                (styles::button::secondary_menu_button("悔棋", Message::Undo))
+                (styles::button::secondary_menu_button("重做", Message::Redo))
+                (styles::button::secondary_menu_button("停一手", Message::Pass))
+                (styles::button::secondary_menu_button("摆棋", Message::ToggleEditMode))
                 (styles::button::secondary_menu_button("添加引擎...", Message::AddEngineButton))
             ))
             )
@@ -304,6 +743,14 @@ impl RBoard {
             menu_template(all_board).max_width(300.0)
             )
             (
+            text("开局"),
+            menu_template(all_opening)
+            )
+            (
+            text("对弈"),
+            menu_template(all_mode)
+            )
+            (
             text("引擎"),
             menu_template(engine_path).max_width(e_len as f32 * 10.0)
             )
@@ -330,12 +777,19 @@ impl RBoard {
             .width(Length::Fill);
         let engine_setting_name;
         let engine_setting_arg;
+        let mut engine_setting_protocol = None;
         if let Some(i) = self.engine_setting_selected {
             let engine_arg = self.engine_path.paths[i].clone();
             engine_setting_name = text_input("", &engine_arg.path)
                 .on_input(move |name| Message::ChangeEngineName(i, name));
             engine_setting_arg = text_editor(&self.engine_setting_arg_content)
                 .on_action(move |action| Message::ChangeEngineArgs(i, action));
+            engine_setting_protocol = Some(
+                pick_list(Protocol::ALL, Some(engine_arg.protocol), move |protocol| {
+                    Message::ChangeEngineProtocol(i, protocol)
+                })
+                .width(110.0),
+            );
         } else {
             engine_setting_name = text_input("", "");
             engine_setting_arg = text_editor(&self.engine_setting_arg_content);
@@ -349,16 +803,195 @@ impl RBoard {
             ]
             .width(130.0)
             .spacing(3.0),
-            column![engine_setting_name, engine_setting_arg].spacing(3.0)
+            column![
+                row![engine_setting_name]
+                    .push_maybe(engine_setting_protocol)
+                    .spacing(3.0),
+                engine_setting_arg
+            ]
+            .spacing(3.0)
         ]
         .spacing(5.0)
         .height(100.0);
 
+        //board size
+        let size_dialog = row![
+            text(format!(
+                "棋盘大小（{} - {}）",
+                MIN_BOARD_SIZE, MAX_BOARD_SIZE
+            )),
+            text_input("宽", &self.board_width)
+                .on_input(Message::ChangeBoardWidth)
+                .on_submit(Message::ConfirmBoardSize)
+                .width(60.0),
+            text("*"),
+            text_input("高", &self.board_height)
+                .on_input(Message::ChangeBoardHeight)
+                .on_submit(Message::ConfirmBoardSize)
+                .width(60.0),
+            button("确定").on_press(Message::ConfirmBoardSize),
+            button("取消").on_press(Message::CloseSizeDialog),
+        ]
+        .spacing(5.0)
+        .align_y(iced::Alignment::Center);
+
+        //engine match
+        let engine_names = self
+            .engine_path
+            .paths
+            .iter()
+            .map(|e| e.name.clone())
+            .collect::<Vec<String>>();
+        let match_engine = |slot: usize| {
+            pick_list(
+                engine_names.clone(),
+                self.match_engines[slot].and_then(|i| engine_names.get(i).cloned()),
+                move |name| Message::ChangeMatchEngine(slot, name),
+            )
+            .width(150.0)
+        };
+        let match_button = if self.match_stop.is_some() {
+            button("停止").on_press(Message::StopMatch)
+        } else {
+            button("开始").on_press(Message::StartMatch)
+        };
+        let score = self.match_score;
+        let (elo, error) = score.elo();
+        let mut match_games = Row::new().spacing(2.0);
+        for (i, record) in self.match_records.iter().enumerate() {
+            let first_black = if record.first_black { "●" } else { "○" };
+            match_games = match_games.push(
+                button(
+                    text(format!(
+                        "{}{} {}",
+                        record.index + 1,
+                        first_black,
+                        record.result
+                    ))
+                    .size(11.0),
+                )
+                .padding(2.0)
+                .style(button::secondary)
+                .on_press(Message::SelectMatchGame(i)),
+            );
+        }
+        let match_dialog = column![
+            row![
+                match_engine(0),
+                text("对"),
+                match_engine(1),
+                text_input("局数", &self.match_games)
+                    .on_input(Message::ChangeMatchGames)
+                    .width(60.0),
+                text("局"),
+                button("开局库...").on_press(Message::OpenMatchOpeningsButton),
+                text(format!("{} 个开局", self.match_openings.len())),
+                match_button,
+                button("关闭").on_press(Message::CloseMatchDialog),
+            ]
+            .spacing(5.0)
+            .align_y(iced::Alignment::Center),
+            text(format!(
+                "{} 局：胜 {} 负 {} 和 {}，Elo {:+.1} ± {:.1}  {}",
+                score.games(),
+                score.wins,
+                score.losses,
+                score.draws,
+                elo,
+                error,
+                self.match_status
+            )),
+            scrollable(match_games.wrap()).height(50.0),
+        ]
+        .spacing(3.0);
+
+        //edit mode
+        let to_play = self.board_state.chessboard.get_player();
+        let edit_panel = row![
+            text("摆棋：左键黑子，右键白子，点击同色棋子清除"),
+            button("黑先")
+                .style(if to_play == Player::Black {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .on_press(Message::SetToPlay(Player::Black)),
+            button("白先")
+                .style(if to_play == Player::White {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .on_press(Message::SetToPlay(Player::White)),
+            button("完成").on_press(Message::ToggleEditMode),
+        ]
+        .spacing(5.0)
+        .align_y(iced::Alignment::Center);
+
+        let edit_confirm = row![
+            text("摆棋会把当前局面作为初始局面，并清除棋谱中的全部棋步和变化"),
+            button("确定").on_press(Message::ConfirmEditMode),
+            button("取消").on_press(Message::CloseEditConfirm),
+        ]
+        .spacing(5.0)
+        .align_y(iced::Alignment::Center);
+
         //board-
+        let result = self.board_state.get_result();
+        let result_text = match &result {
+            Some(GameResult {
+                winner: Some(Player::Black),
+                ..
+            }) => "黑胜",
+            Some(GameResult {
+                winner: Some(Player::White),
+                ..
+            }) => "白胜",
+            Some(GameResult { winner: None, .. }) => "和棋",
+            None => "",
+        };
+        let mut opening_panel = Column::new().spacing(3.0);
+        if let Some(opening) = &self.opening {
+            opening_panel = opening_panel.push(text(opening.prompt()));
+            let mut choices = Row::new().spacing(2.0);
+            for choice in opening.choices() {
+                choices = choices.push(
+                    button(text(choice.label()).size(12.0))
+                        .on_press(Message::OpeningChoose(choice)),
+                );
+            }
+            opening_panel = opening_panel.push(choices.wrap());
+        }
         let board = canvas(Board {
             count: self.board_state.chessboard.get_length(),
             pieces: self.board_state.chessboard.get_pieces(),
             analyzes: Arc::clone(&self.analyzes),
+            analysis_points: self
+                .analyzes
+                .datas
+                .iter()
+                .map(
+                    |data| match self.board_state.chessboard.get_move(&data.move_) {
+                        Some(Move::Place(x, y)) => Some((x, y)),
+                        _ => None,
+                    },
+                )
+                .collect(),
+            win_line: result.as_ref().map(|r| r.line.clone()).unwrap_or_default(),
+            marks: match &self.opening {
+                Some(opening) => {
+                    [self.board_state.chessboard.get_marks(), opening.marks()].concat()
+                }
+                None => self.board_state.chessboard.get_marks(),
+            },
+            turn: self
+                .board_state
+                .chessboard
+                .get_turn()
+                .map(|(placed, total)| (self.board_state.chessboard.get_player(), placed, total)),
+            shape: self.board_state.chessboard.get_shape(),
+            targets: self.board_targets(),
+            edit: self.edit_mode,
         })
         .width(Length::Fill)
         .height(Length::Fill);
@@ -375,6 +1008,37 @@ impl RBoard {
             .min_width(size.width);
             analyze_table.into()
         });
+        //引擎既不支持 kata-analyze 也不支持 lz-analyze 时不显示分析表格
+        let analyze_table: iced::Element<Message> =
+            if self.supports(|c| c.analyze_command().is_none()) {
+                container(text("引擎不支持分析").size(12.0))
+                    .width(Length::Fill)
+                    .into()
+            } else {
+                analyze_table.into()
+            };
+
+        //game tree
+        let tree_nav = row![
+            button("|<").on_press(Message::FirstMove),
+            button("<").on_press(Message::Undo),
+            button(">").on_press(Message::Redo),
+            button(">|").on_press(Message::LastMove),
+            button("↑").on_press(Message::SwitchBranch(-1)),
+            button("↓").on_press(Message::SwitchBranch(1)),
+            button("停一手").on_press(Message::Pass),
+            button("认输").on_press(Message::Resign),
+        ]
+        .spacing(2.0);
+        let comment = text_input("注释", &self.board_state.tree.current_node().comment)
+            .on_input(Message::ChangeComment);
+        let tree_panel = column![
+            tree_nav,
+            comment,
+            game_tree::view(&self.board_state.tree, self.board_state.chessboard.as_ref())
+        ]
+        .spacing(3.0)
+        .width(300.0);
 
         let start_index = self.engine_msg.len().saturating_sub(100);
         let engine_output = selection_list::SelectionList::new_with(
@@ -396,39 +1060,402 @@ impl RBoard {
                     .color(Color::from_rgb8(211, 211, 211))
                     .width(2.0),
             });
+        let rate: iced::Element<Message> = match self.board_state.chessboard.get_captures() {
+            Some((black, white)) => row![
+                text(format!("提 {}", black)).size(12.0),
+                rate,
+                text(format!("提 {}", white)).size(12.0)
+            ]
+            .spacing(3.0)
+            .align_y(iced::Alignment::Center)
+            .into(),
+            None => rate.into(),
+        };
         // Render the chessboard and pieces
         let mut main_view = Column::new().push(menu_bar);
         if self.show_engine_manager {
             main_view = main_view.push(engine_setting);
         }
+        if self.show_size_dialog {
+            main_view = main_view.push(size_dialog);
+        }
+        if self.show_match_dialog {
+            main_view = main_view.push(match_dialog);
+        }
+        if self.show_edit_confirm {
+            main_view = main_view.push(edit_confirm);
+        }
+        if self.edit_mode {
+            main_view = main_view.push(edit_panel);
+        }
         main_view
             .push(
-                row![column![rate, engine_output].spacing(5.0).width(250), board]
+                row![
+                    column![
+                        rate,
+                        text(result_text),
+                        text(self.board_state.chessboard.get_info().unwrap_or_default()),
+                        text(
+                            self.engine_rules
+                                .as_ref()
+                                .map(|rules| format!("引擎规则: {}", rules))
+                                .unwrap_or_default()
+                        )
+                        .size(12.0),
+                        text(if self.genmove_id.is_some() {
+                            "引擎思考中..."
+                        } else {
+                            ""
+                        }),
+                        opening_panel,
+                        engine_output
+                    ]
                     .spacing(5.0)
-                    .height(Length::Fill),
+                    .width(250),
+                    board
+                ]
+                .spacing(5.0)
+                .height(Length::Fill),
             )
-            .push(column![analyze_table].height(100.0))
+            .push(row![analyze_table, tree_panel].spacing(5.0).height(160.0))
             .padding(10)
             .spacing(5)
             .into()
     }
 
+    /// 进入摆棋：当前局面作为初始局面，停止分析。
+    fn start_edit(&mut self) {
+        self.edit_mode = true;
+        self.opening = None;
+        self.board_state.start_edit();
+        if let Some(gtp) = &self.engine {
+            let _ = gtp.send_command("stop".to_string());
+        }
+        self.engine_analyzing = None;
+        self.analyzes = Arc::new(Default::default());
+        self.engine_analyzes_table.rows = vec![];
+    }
+
+    /// 开始新的一局，`opening` 为这一局使用的开局规则。
+    fn new_board(&mut self, opening: Option<Opening>) {
+        self.board_state.new_board();
+        //先设置开局规则，开局期间引擎不落子
+        self.opening = opening;
+        self.edit_mode = false;
+        self.cancel_genmove();
+        if let Some(gtp) = &self.engine {
+            let _ = gtp.send_command("stop".to_string());
+            let _ = gtp.send_command("clear_board".to_string());
+            self.analyzes = Arc::new(Default::default());
+            self.engine_analyzes_table.rows = vec![];
+            self.engine_msg.clear();
+            self.resume_analyze();
+        }
+    }
+
+    //点击每个格子时实际落子的位置
+    fn board_targets(&self) -> Vec<Vec<Option<(u32, u32)>>> {
+        let chessboard = &self.board_state.chessboard;
+        let (x, y) = chessboard.get_length();
+        (0..x as i32)
+            .map(|i| {
+                (0..y as i32)
+                    .map(|j| {
+                        chessboard
+                            .resolve(i, j)
+                            .map(|(tx, ty)| (tx as u32, ty as u32))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    //载入棋谱之后的处理，`name` 为载入前的棋盘类型
+    fn after_load(&mut self, name: String, loaded: Result<Vec<String>, String>) {
+        match loaded {
+            Ok(cmds) => {
+                self.opening = None;
+                self.edit_mode = false;
+                self.cancel_genmove();
+                //棋盘类型变了，当前引擎不再适用
+                if self.board_state.name != name {
+                    self.close_engine();
+                } else {
+                    self.sync_engine(cmds);
+                }
+            }
+            Err(e) => eprintln!("open record err: {}", e),
+        }
+    }
+
+    fn close_engine(&mut self) {
+        self.genmove_id = None;
+        self.engine_analyzing = None;
+        self.engine_capabilities = None;
+        self.engine_name = None;
+        self.engine_version = None;
+        self.engine_rules = None;
+        if let Some(mut gtp) = self.engine.take() {
+            let _ = gtp.send_command("stop".to_string());
+            let _ = gtp.exit();
+            self.analyzes = Arc::new(Default::default());
+            self.engine_analyzes_table.rows = vec![];
+            self.engine_msg.clear();
+        }
+    }
+
+    /// 重新同步引擎局面：清空棋盘（或摆出初始局面）后按顺序重放全部落子，再继续分析。
+    fn sync_engine(&mut self, cmds: Vec<String>) {
+        if let Some(gtp) = &self.engine
+            && let Some(caps) = &self.engine_capabilities
+        {
+            //载入的棋谱可能带有不同的贴目
+            if caps.komi
+                && let Some(komi) = self.board_state.chessboard.get_komi()
+            {
+                let _ = gtp.send_command(format!("komi {}", komi));
+            }
+            let setup = self.board_state.setup_commands(caps.set_position);
+            for cmd in setup.into_iter().chain(cmds) {
+                let _ = gtp.send_command(cmd);
+            }
+        }
+        self.analyzes = Arc::new(Default::default());
+        self.engine_analyzes_table.rows = vec![];
+        self.resume_analyze();
+    }
+
+    /// 收到 list_commands 的回复后设置棋盘大小、规则和贴目，再同步当前局面。
+    /// 引擎不支持的命令不发送，不能 genmove 的引擎退出人机对弈。
+    fn init_engine(&mut self) {
+        let (Some(gtp), Some(caps)) = (&self.engine, &self.engine_capabilities) else {
+            return;
+        };
+        let chessboard = &self.board_state.chessboard;
+        let _ = gtp.send_boardsize(chessboard.get_length());
+        if caps.kata_set_rules
+            && let Some(rules) = chessboard.get_rules()
+        {
+            let _ = gtp.send_command(format!("kata-set-rules {}", rules));
+        }
+        if caps.kata_get_rules {
+            let _ = gtp.send_command("kata-get-rules".to_string());
+        }
+        if !caps.can_genmove() {
+            self.engine_color = None;
+        }
+        self.check_board_size();
+        //新引擎需要同步当前局面，人机对弈时可能正轮到引擎
+        let cmds = self.board_state.replay();
+        self.sync_engine(cmds);
+    }
+
+    //引擎不支持长方形棋盘时在引擎输出中提示
+    fn check_board_size(&mut self) {
+        let size = self.board_state.chessboard.get_length();
+        if !self.supports(|c| c.supports_size(size)) {
+            self.engine_msg
+                .push("引擎不支持长方形棋盘 (rectangular_boardsize)".to_string());
+        }
+    }
+
+    /// 引擎是否支持某项功能，还没有收到 list_commands 的回复时为 `false`。
+    fn supports(&self, f: impl Fn(&Capabilities) -> bool) -> bool {
+        self.engine_capabilities.as_ref().is_some_and(f)
+    }
+
+    /// 人机对弈时是否轮到引擎落子。开局规则进行期间由玩家落子和选择，开局结束后引擎才落子；
+    /// 对局结束后不再轮到任何一方，玩家可以点目。
+    fn is_engine_turn(&self) -> bool {
+        !self.edit_mode
+            && self.board_state.get_result().is_none()
+            && self.opening.as_ref().is_none_or(|o| o.is_done())
+            && self.engine_color == Some(self.board_state.chessboard.get_player())
+    }
+
+    //局面被重置，之后收到的那条 genmove 回复已经过时
+    fn cancel_genmove(&mut self) {
+        self.genmove_id = None;
+    }
+
+    /// 处理引擎对命令的回复：记下引擎的名称、版本、规则和支持的命令，genmove 的回复下在棋盘上。
+    fn receive_response(&mut self, response: Response) {
+        if self.genmove_id == Some(response.id) {
+            self.receive_genmove(response);
+            return;
+        }
+        if !response.success {
+            //list_commands 是 GTP 必须支持的命令，失败时当作什么都不支持
+            if response.name() == "list_commands" {
+                self.engine_capabilities = Some(Capabilities::default());
+                self.init_engine();
+            }
+            return;
+        }
+        match response.name() {
+            "name" => self.engine_name = Some(response.body),
+            "version" => self.engine_version = Some(response.body),
+            "kata-get-rules" => self.engine_rules = Some(response.body),
+            "list_commands" => {
+                self.engine_capabilities = Some(Capabilities::from_list_commands(&response.body));
+                self.init_engine();
+            }
+            _ => {}
+        }
+    }
+
+    /// 处理 genmove 的回复：`= G3`、`= pass`、`= resign`（genmove_analyze 为 `= play G3`）下在棋盘上，
+    /// `?` 或者无法识别的回复表示引擎无法落子，此时退出人机对弈。
+    fn receive_genmove(&mut self, response: Response) {
+        self.genmove_id = None;
+        self.engine_analyzing = None;
+        let mv = if response.success {
+            //genmove_analyze 的回复为 `play G3`
+            let body = response.body.trim();
+            self.board_state
+                .chessboard
+                .get_move(body.strip_prefix("play").unwrap_or(body))
+        } else {
+            None
+        };
+        let cmds = match mv {
+            Some(Move::Place(x, y)) => self.board_state.go(x as i32, y as i32),
+            Some(Move::Pass) => self.board_state.pass(),
+            Some(Move::Resign) => self.board_state.resign().then(Vec::new),
+            None => None,
+        };
+        let Some(cmds) = cmds else {
+            eprintln!("genmove err: {}", response);
+            self.engine_color = None;
+            let cmds = self.board_state.replay();
+            self.sync_engine(cmds);
+            return;
+        };
+        //引擎已经记下了这一手，只需要发送之后自动停的一手
+        if let Some(gtp) = &self.engine {
+            for cmd in cmds.into_iter().skip(1) {
+                let _ = gtp.send_command(cmd);
+            }
+        }
+        if let Some(opening) = &mut self.opening {
+            let tree = &self.board_state.tree;
+            opening.after_move(tree.depth(tree.current()));
+        }
+        self.resume_analyze();
+    }
+
+    /// 继续分析当前局面；对局已经结束或者一回合还没下完时停止分析并清空分析结果。
+    /// 人机对弈时轮到引擎则改为 `genmove`，引擎思考期间暂停分析。
+    fn resume_analyze(&mut self) {
+        let (Some(gtp), Some(caps)) = (&self.engine, &self.engine_capabilities) else {
+            return;
+        };
+        let player = self.board_state.chessboard.get_player();
+        if self.board_state.get_result().is_some() {
+            let _ = gtp.send_command("stop".to_string());
+            self.engine_analyzing = None;
+            self.analyzes = Arc::new(Default::default());
+            self.engine_analyzes_table.rows = vec![];
+        } else if self.is_engine_turn() {
+            //支持 genmove_analyze 时引擎思考期间也显示分析
+            if self.genmove_id.is_none() {
+                let command = caps.genmove_analyze_command();
+                let handle = match command {
+                    Some(command) => gtp.send_genmove_analyze(command, player),
+                    None => gtp.send_genmove(player),
+                };
+                if let Ok(handle) = handle {
+                    self.genmove_id = Some(handle.id());
+                    self.engine_analyzing = command;
+                }
+            }
+            self.analyzes = Arc::new(Default::default());
+            self.engine_analyzes_table.rows = vec![];
+        } else if self
+            .board_state
+            .chessboard
+            .get_turn()
+            .is_some_and(|(placed, _)| placed > 0)
+        {
+            //一回合下多子时，回合中途的局面不完整，下完本回合再分析
+            let _ = gtp.send_command("stop".to_string());
+            self.engine_analyzing = None;
+            self.analyzes = Arc::new(Default::default());
+            self.engine_analyzes_table.rows = vec![];
+        } else if let Some(command) = caps.analyze_command() {
+            let _ = gtp.send_analyze(command, player);
+            self.engine_analyzing = Some(command);
+        }
+    }
+
     fn subscription(&self) -> Subscription<Message> {
-        Subscription::run(get_data)
+        Subscription::batch([
+            Subscription::run(get_data),
+            keyboard::on_key_press(key_press),
+        ])
     }
 
     fn title(&self) -> String {
         if let Some(i) = self.engine_path.current_path {
-            format!("RBoard - {}", self.engine_path.paths[i as usize].name)
+            let name = &self.engine_path.paths[i as usize].name;
+            //引擎回复了 name 和 version 时一起显示
+            match (&self.engine_name, &self.engine_version) {
+                (Some(engine), Some(version)) => {
+                    format!("RBoard - {} ({} {})", name, engine, version)
+                }
+                (Some(engine), None) => format!("RBoard - {} ({})", name, engine),
+                _ => format!("RBoard - {}", name),
+            }
         } else {
             "RBoard".to_string()
         }
     }
 }
 
+/// 快捷键：← 悔棋，→ 重做，Home/End 回到开局/最后一手，↑/↓ 切换变化，P 停一手，R 认输。
+/// 输入框获得焦点时按键会被输入框处理，不会触发快捷键。
+fn key_press(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+    use keyboard::{Key, key::Named};
+    if !modifiers.is_empty() {
+        return None;
+    }
+    match key.as_ref() {
+        Key::Named(Named::ArrowLeft) => Some(Message::Undo),
+        Key::Named(Named::ArrowRight) => Some(Message::Redo),
+        Key::Named(Named::Home) => Some(Message::FirstMove),
+        Key::Named(Named::End) => Some(Message::LastMove),
+        Key::Named(Named::ArrowUp) => Some(Message::SwitchBranch(-1)),
+        Key::Named(Named::ArrowDown) => Some(Message::SwitchBranch(1)),
+        Key::Character("p") => Some(Message::Pass),
+        Key::Character("r") => Some(Message::Resign),
+        _ => None,
+    }
+}
+
+/// 在后台线程中进行引擎对战，每下完一局发出 `Message::MatchGame`，结束时发出 `Message::MatchFinished`。
+fn match_events(
+    config: MatchConfig,
+    first: EngineArgs,
+    second: EngineArgs,
+    stop: Arc<AtomicBool>,
+) -> impl Stream<Item = Message> {
+    iced::stream::channel(100, |mut output| async move {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded::<Message>();
+        std::thread::spawn(move || {
+            let result = run_match(&config, &first, &second, &stop, |record| {
+                let _ = tx.unbounded_send(Message::MatchGame(record));
+            });
+            let _ = tx.unbounded_send(Message::MatchFinished(result));
+        });
+        while let Some(message) = rx.next().await {
+            let _ = output.send(message).await;
+        }
+    })
+}
+
 fn get_data() -> impl Stream<Item = Message> {
     iced::stream::channel(1000, |mut output| async move {
-        let (sender, mut receiver) = iced::futures::channel::mpsc::channel::<String>(1000);
+        let (sender, mut receiver) = iced::futures::channel::mpsc::channel::<EngineOutput>(1000);
         let _ = output.send(Message::EngineSender(sender)).await;
         loop {
             let input = receiver.select_next_some().await;
@@ -1,11 +1,43 @@
 use iced::widget::{scrollable, text_editor};
 use rfd::FileHandle;
 
+use crate::chessboard::chessboard_trait::Player;
+use crate::engine::engine_args::Protocol;
+use crate::engine::gtp::EngineOutput;
+use crate::engine::match_runner::GameRecord;
+use crate::opening::{OpeningChoice, OpeningProtocol};
+
 #[derive(Debug, Clone)]
 pub enum Message {
     NewBoard,
     GoBoard(i32, i32),
+    Pass,
+    Resign,
+    Undo,
+    Redo,
+    FirstMove,
+    LastMove,
+    SwitchBranch(i32),
+    SelectNode(usize),
+    ChangeComment(String),
+    ToggleEditMode,
+    ConfirmEditMode,
+    CloseEditConfirm,
+    EditBoard(i32, i32, Player),
+    SetToPlay(Player),
+    SetEngineColor(Option<Player>),
+    OpenRecordButton,
+    OpenRecord(Option<FileHandle>),
+    SaveRecordButton,
+    SaveRecord(Option<FileHandle>),
     ChangeBoard(String),
+    OpenSizeDialog,
+    CloseSizeDialog,
+    ChangeBoardWidth(String),
+    ChangeBoardHeight(String),
+    ConfirmBoardSize,
+    StartOpening(Option<OpeningProtocol>),
+    OpeningChoose(OpeningChoice),
     AddEngineButton,
     AddEngige(Option<FileHandle>),
     ChangeEngine(usize),
@@ -15,9 +47,21 @@ pub enum Message {
     EngineTableSyncHeader(scrollable::AbsoluteOffset),
     ChangeEngineName(usize, String),
     ChangeEngineArgs(usize, text_editor::Action),
+    ChangeEngineProtocol(usize, Protocol),
     DeleteEngine,
     EngineOutputSelected(usize, String),
-    EngineSender(iced::futures::channel::mpsc::Sender<String>),
-    EngineReceiveOutput(String),
+    EngineSender(iced::futures::channel::mpsc::Sender<EngineOutput>),
+    EngineReceiveOutput(EngineOutput),
     CloseEngine,
+    OpenMatchDialog,
+    CloseMatchDialog,
+    ChangeMatchEngine(usize, String),
+    ChangeMatchGames(String),
+    OpenMatchOpeningsButton,
+    OpenMatchOpenings(Option<FileHandle>),
+    StartMatch,
+    StopMatch,
+    MatchGame(GameRecord),
+    MatchFinished(Result<(), String>),
+    SelectMatchGame(usize),
 }
@@ -0,0 +1,513 @@
+use iced::Color;
+
+use crate::chessboard::chessboard_trait::{Mark, Player};
+
+/// 开局规则。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpeningProtocol {
+    /// 一方摆三子，另一方选择执黑或执白
+    Swap,
+    /// 一方摆三子，另一方选择执黑、执白，或者再下两子交由对方选择
+    Swap2,
+    /// 索索夫-8：摆三子可交换，第四手后可交换，宣布第五手候选数后可交换，
+    /// 黑方摆出候选点，白方从中挑选第五手
+    Soosorv,
+}
+
+impl OpeningProtocol {
+    pub fn all() -> Vec<(String, OpeningProtocol)> {
+        vec![
+            ("Swap".to_string(), OpeningProtocol::Swap),
+            ("Swap2".to_string(), OpeningProtocol::Swap2),
+            ("Soosõrv-8".to_string(), OpeningProtocol::Soosorv),
+        ]
+    }
+}
+
+/// 开局阶段中的两位玩家，`First` 为摆出前三子的一方。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Seat {
+    First,
+    Second,
+}
+
+impl Seat {
+    pub fn other(self) -> Seat {
+        match self {
+            Seat::First => Seat::Second,
+            Seat::Second => Seat::First,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Seat::First => "玩家一",
+            Seat::Second => "玩家二",
+        }
+    }
+}
+
+/// 开局阶段中玩家可以做出的选择。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpeningChoice {
+    /// 执黑
+    Black,
+    /// 执白
+    White,
+    /// 再下两子（一白一黑），由对方选择执黑或执白
+    PlaceTwo,
+    /// 宣布第五手候选点的数量
+    Declare(usize),
+    /// 摆完第五手候选点，交给对方挑选
+    ConfirmCandidates,
+}
+
+impl OpeningChoice {
+    pub fn label(&self) -> String {
+        match self {
+            OpeningChoice::Black => "执黑".to_string(),
+            OpeningChoice::White => "执白".to_string(),
+            OpeningChoice::PlaceTwo => "再下两子".to_string(),
+            OpeningChoice::Declare(n) => format!("{} 个五手", n),
+            OpeningChoice::ConfirmCandidates => "确定候选点".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpeningStage {
+    /// `seat` 落子，直到棋盘上共有 `until` 手棋
+    Place { seat: Seat, until: usize },
+    /// `seat` 从 `choices` 中做出选择
+    Choose {
+        seat: Seat,
+        choices: Vec<OpeningChoice>,
+    },
+    /// 执黑一方摆出 `n` 个第五手候选点，摆满后确认
+    Candidates { n: usize },
+    /// 执白一方从候选点中挑选第五手
+    SelectFifth,
+    /// 开局结束，双方正常对弈
+    Done,
+}
+
+/// 开局规则的状态机，叠加在棋盘之上，决定哪些点击可以落子以及双方的执子颜色。
+pub struct Opening {
+    pub protocol: OpeningProtocol,
+    pub stage: OpeningStage,
+    //执黑的一方
+    black: Seat,
+    //索索夫的第五手候选点
+    candidates: Vec<(i32, i32)>,
+    //索索夫中已经做出的黑白选择次数
+    swaps: usize,
+    //索索夫宣布的第五手候选数
+    declared: usize,
+}
+
+impl Opening {
+    pub fn new(protocol: OpeningProtocol) -> Self {
+        Opening {
+            protocol,
+            stage: OpeningStage::Place {
+                seat: Seat::First,
+                until: 3,
+            },
+            black: Seat::First,
+            candidates: vec![],
+            swaps: 0,
+            declared: 0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.stage == OpeningStage::Done
+    }
+
+    /// 执 `player` 的一方。
+    pub fn seat(&self, player: Player) -> Seat {
+        match player {
+            Player::Black => self.black,
+            Player::White => self.black.other(),
+        }
+    }
+
+    /// `seat` 执的颜色。
+    pub fn color(&self, seat: Seat) -> Player {
+        if seat == self.black {
+            Player::Black
+        } else {
+            Player::White
+        }
+    }
+
+    /// 执黑、执白的玩家名字，用于写入棋谱的 `PB`、`PW`。
+    pub fn player_names(&self) -> (String, String) {
+        (
+            self.black.name().to_string(),
+            self.black.other().name().to_string(),
+        )
+    }
+
+    /// 处理棋盘上的点击。
+    ///
+    /// # 返回值
+    /// - `Some((x, y))`：该点击应当作为一手棋下到棋盘上。
+    /// - `None`：当前阶段不允许落子，或点击只是记录了候选点。
+    ///   再次点击候选点会取消它，摆满之后需要选择“确定候选点”才交给对方挑选。
+    pub fn click(&mut self, x: i32, y: i32) -> Option<(i32, i32)> {
+        match &self.stage {
+            OpeningStage::Place { .. } | OpeningStage::Done => Some((x, y)),
+            OpeningStage::Choose { .. } => None,
+            OpeningStage::Candidates { n } => {
+                if let Some(i) = self.candidates.iter().position(|&c| c == (x, y)) {
+                    self.candidates.remove(i);
+                } else if self.candidates.len() < *n {
+                    self.candidates.push((x, y));
+                }
+                None
+            }
+            OpeningStage::SelectFifth => self.candidates.contains(&(x, y)).then_some((x, y)),
+        }
+    }
+
+    /// 棋盘上成功落子后调用，`moves` 为当前的总手数。
+    pub fn after_move(&mut self, moves: usize) {
+        match self.stage {
+            OpeningStage::Place { until, .. } if moves >= until => {
+                self.stage = match (self.protocol, moves) {
+                    (OpeningProtocol::Swap, _) => OpeningStage::Choose {
+                        seat: Seat::Second,
+                        choices: vec![OpeningChoice::Black, OpeningChoice::White],
+                    },
+                    (OpeningProtocol::Swap2, 3) => OpeningStage::Choose {
+                        seat: Seat::Second,
+                        choices: vec![
+                            OpeningChoice::Black,
+                            OpeningChoice::White,
+                            OpeningChoice::PlaceTwo,
+                        ],
+                    },
+                    (OpeningProtocol::Swap2, _) => OpeningStage::Choose {
+                        seat: Seat::First,
+                        choices: vec![OpeningChoice::Black, OpeningChoice::White],
+                    },
+                    (OpeningProtocol::Soosorv, 3) => OpeningStage::Choose {
+                        seat: Seat::Second,
+                        choices: vec![OpeningChoice::Black, OpeningChoice::White],
+                    },
+                    //第四手之后，执黑一方可以交换
+                    (OpeningProtocol::Soosorv, _) => OpeningStage::Choose {
+                        seat: self.black,
+                        choices: vec![OpeningChoice::Black, OpeningChoice::White],
+                    },
+                };
+            }
+            OpeningStage::SelectFifth => {
+                self.candidates.clear();
+                self.stage = OpeningStage::Done;
+            }
+            _ => {}
+        }
+    }
+
+    /// 悔棋时在开局规则之内退一步：撤销最后一个候选点，或者从挑选第五手回到摆候选点。
+    ///
+    /// # 返回值
+    /// 已经在开局规则之内处理时返回 `true`，棋盘不需要悔棋；否则返回 `false`。
+    pub fn undo(&mut self) -> bool {
+        match self.stage {
+            OpeningStage::Candidates { .. } => self.candidates.pop().is_some(),
+            OpeningStage::SelectFifth => {
+                self.stage = OpeningStage::Candidates { n: self.declared };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 当前阶段的玩家做出选择。
+    pub fn choose(&mut self, choice: OpeningChoice) {
+        if let OpeningStage::Candidates { n } = self.stage {
+            if choice == OpeningChoice::ConfirmCandidates && self.candidates.len() == n {
+                self.stage = OpeningStage::SelectFifth;
+            }
+            return;
+        }
+        let OpeningStage::Choose { seat, choices } = &self.stage else {
+            return;
+        };
+        let seat = *seat;
+        if !choices.contains(&choice) {
+            return;
+        }
+        match choice {
+            OpeningChoice::Black => self.black = seat,
+            OpeningChoice::White => self.black = seat.other(),
+            _ => {}
+        }
+        self.stage = match (self.protocol, choice) {
+            (OpeningProtocol::Swap2, OpeningChoice::PlaceTwo) => {
+                OpeningStage::Place { seat, until: 5 }
+            }
+            (OpeningProtocol::Soosorv, OpeningChoice::Declare(n)) => {
+                self.declared = n;
+                OpeningStage::Choose {
+                    seat: self.black.other(),
+                    choices: vec![OpeningChoice::Black, OpeningChoice::White],
+                }
+            }
+            (OpeningProtocol::Soosorv, _) => {
+                self.swaps += 1;
+                match self.swaps {
+                    //第一次选择后，执白一方下第四手
+                    1 => OpeningStage::Place {
+                        seat: self.black.other(),
+                        until: 4,
+                    },
+                    //第二次选择后，执黑一方宣布第五手候选数
+                    2 => OpeningStage::Choose {
+                        seat: self.black,
+                        choices: (1..=8).map(OpeningChoice::Declare).collect(),
+                    },
+                    //第三次选择后，执黑一方摆出候选点
+                    _ => OpeningStage::Candidates { n: self.declared },
+                }
+            }
+            _ => OpeningStage::Done,
+        };
+    }
+
+    /// 当前阶段的提示文字。
+    pub fn prompt(&self) -> String {
+        match &self.stage {
+            OpeningStage::Place { seat, until } => {
+                format!("{}落子，直到第 {} 手", seat.name(), until)
+            }
+            OpeningStage::Choose { seat, .. } => format!("{}选择", seat.name()),
+            OpeningStage::Candidates { n } => format!(
+                "{}摆出 {} 个第五手候选点（已摆 {} 个）",
+                self.black.name(),
+                n,
+                self.candidates.len()
+            ),
+            OpeningStage::SelectFifth => format!("{}选择第五手", self.black.other().name()),
+            OpeningStage::Done => {
+                let (black, white) = self.player_names();
+                format!("开局结束：{}执黑，{}执白", black, white)
+            }
+        }
+    }
+
+    /// 当前阶段可以做出的选择。
+    pub fn choices(&self) -> Vec<OpeningChoice> {
+        match &self.stage {
+            OpeningStage::Choose { choices, .. } => choices.clone(),
+            OpeningStage::Candidates { n } if self.candidates.len() == *n => {
+                vec![OpeningChoice::ConfirmCandidates]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// 第五手候选点的标记。
+    pub fn marks(&self) -> Vec<(u32, u32, Mark)> {
+        self.candidates
+            .iter()
+            .map(|&(x, y)| {
+                (
+                    x as u32,
+                    y as u32,
+                    Mark::Dot(Color::from_rgb8(25, 118, 210)),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_colors() -> Vec<OpeningChoice> {
+        vec![OpeningChoice::Black, OpeningChoice::White]
+    }
+
+    #[test]
+    fn swap() {
+        let mut opening = Opening::new(OpeningProtocol::Swap);
+        assert_eq!(opening.click(7, 7), Some((7, 7)));
+        opening.after_move(1);
+        opening.after_move(2);
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Place {
+                seat: Seat::First,
+                until: 3
+            }
+        );
+        opening.after_move(3);
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Choose {
+                seat: Seat::Second,
+                choices: two_colors()
+            }
+        );
+        //选择阶段不能落子，也不接受其他选择
+        assert_eq!(opening.click(8, 8), None);
+        opening.choose(OpeningChoice::PlaceTwo);
+        assert!(!opening.is_done());
+        opening.choose(OpeningChoice::Black);
+        assert!(opening.is_done());
+        assert_eq!(opening.color(Seat::Second), Player::Black);
+        assert_eq!(opening.seat(Player::White), Seat::First);
+        assert_eq!(
+            opening.player_names(),
+            ("玩家二".to_string(), "玩家一".to_string())
+        );
+    }
+
+    #[test]
+    fn swap2() {
+        let mut opening = Opening::new(OpeningProtocol::Swap2);
+        opening.after_move(3);
+        assert_eq!(
+            opening.choices(),
+            vec![
+                OpeningChoice::Black,
+                OpeningChoice::White,
+                OpeningChoice::PlaceTwo
+            ]
+        );
+        opening.choose(OpeningChoice::PlaceTwo);
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Place {
+                seat: Seat::Second,
+                until: 5
+            }
+        );
+        opening.after_move(4);
+        assert!(opening.choices().is_empty());
+        opening.after_move(5);
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Choose {
+                seat: Seat::First,
+                choices: two_colors()
+            }
+        );
+        opening.choose(OpeningChoice::White);
+        assert!(opening.is_done());
+        assert_eq!(opening.color(Seat::First), Player::White);
+
+        //第三手之后直接选择颜色
+        let mut opening = Opening::new(OpeningProtocol::Swap2);
+        opening.after_move(3);
+        opening.choose(OpeningChoice::White);
+        assert!(opening.is_done());
+        assert_eq!(opening.color(Seat::First), Player::Black);
+    }
+
+    #[test]
+    fn soosorv() {
+        let mut opening = Opening::new(OpeningProtocol::Soosorv);
+        opening.after_move(3);
+        opening.choose(OpeningChoice::White);
+        //执白的玩家二下第四手
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Place {
+                seat: Seat::Second,
+                until: 4
+            }
+        );
+        opening.after_move(4);
+        //执黑的玩家一可以交换
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Choose {
+                seat: Seat::First,
+                choices: two_colors()
+            }
+        );
+        opening.choose(OpeningChoice::White);
+        //交换后执黑的玩家二宣布候选数，由执白的玩家一选择是否交换
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Choose {
+                seat: Seat::Second,
+                choices: (1..=8).map(OpeningChoice::Declare).collect()
+            }
+        );
+        opening.choose(OpeningChoice::Declare(2));
+        assert_eq!(
+            opening.stage,
+            OpeningStage::Choose {
+                seat: Seat::First,
+                choices: two_colors()
+            }
+        );
+        opening.choose(OpeningChoice::Black);
+        assert_eq!(opening.stage, OpeningStage::Candidates { n: 2 });
+        assert_eq!(opening.color(Seat::First), Player::Black);
+
+        //摆满之前不能确认，摆满之后不再增加候选点
+        assert_eq!(opening.click(1, 1), None);
+        assert!(opening.choices().is_empty());
+        opening.choose(OpeningChoice::ConfirmCandidates);
+        assert_eq!(opening.stage, OpeningStage::Candidates { n: 2 });
+        assert_eq!(opening.click(2, 2), None);
+        assert_eq!(opening.click(3, 3), None);
+        assert_eq!(opening.marks().len(), 2);
+        assert_eq!(opening.choices(), vec![OpeningChoice::ConfirmCandidates]);
+        opening.choose(OpeningChoice::ConfirmCandidates);
+        assert_eq!(opening.stage, OpeningStage::SelectFifth);
+
+        //执白一方只能从候选点中挑选
+        assert_eq!(opening.click(3, 3), None);
+        assert_eq!(opening.click(2, 2), Some((2, 2)));
+        opening.after_move(5);
+        assert!(opening.is_done());
+        assert!(opening.marks().is_empty());
+        assert_eq!(
+            opening.player_names(),
+            ("玩家一".to_string(), "玩家二".to_string())
+        );
+    }
+
+    #[test]
+    fn candidates_can_be_taken_back() {
+        let mut opening = Opening::new(OpeningProtocol::Soosorv);
+        opening.after_move(3);
+        opening.choose(OpeningChoice::Black);
+        opening.after_move(4);
+        opening.choose(OpeningChoice::Black);
+        opening.choose(OpeningChoice::Declare(2));
+        opening.choose(OpeningChoice::Black);
+        assert_eq!(opening.stage, OpeningStage::Candidates { n: 2 });
+        //其他阶段的悔棋交给棋盘处理
+        assert!(!Opening::new(OpeningProtocol::Soosorv).undo());
+        assert!(!opening.undo());
+
+        //再次点击取消候选点
+        opening.click(1, 1);
+        opening.click(2, 2);
+        opening.click(1, 1);
+        assert_eq!(opening.candidates, vec![(2, 2)]);
+        opening.click(3, 3);
+        opening.choose(OpeningChoice::ConfirmCandidates);
+        assert_eq!(opening.stage, OpeningStage::SelectFifth);
+
+        //悔棋先回到摆候选点，再逐个撤销候选点
+        assert!(opening.undo());
+        assert_eq!(opening.stage, OpeningStage::Candidates { n: 2 });
+        assert_eq!(opening.candidates, vec![(2, 2), (3, 3)]);
+        assert!(opening.undo());
+        assert_eq!(opening.candidates, vec![(2, 2)]);
+        assert!(opening.undo());
+        assert!(!opening.undo());
+        assert_eq!(opening.stage, OpeningStage::Candidates { n: 2 });
+    }
+}
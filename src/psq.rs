@@ -0,0 +1,93 @@
+use crate::{chessboard::chessboard_trait::Player, game_tree::GameTree, sgf::Record};
+
+/// 解析 Piskvork / Gomocup 的 `.psq` 棋谱。
+///
+/// 第一行为 `Piskvorky 20x20, 11:11, 0`，之后每行一手 `x,y,time`，
+/// 坐标从 1 开始，x 为列，y 为行（从上往下）。遇到第一行不是落子的内容即结束。
+/// psq 不记录规则，棋盘类型 `name` 由调用者决定，例如当前的连珠棋盘。
+pub fn read(s: &str, name: &str) -> Result<Record, String> {
+    let mut lines = s.lines();
+    let header = lines.next().ok_or("psq 文件为空")?;
+    let size = header
+        .split([' ', ','])
+        .find_map(|part| {
+            let (x, y) = part.split_once('x')?;
+            Some((x.parse::<u32>().ok()?, y.parse::<u32>().ok()?))
+        })
+        .ok_or(format!("无法解析 psq 文件头: {}", header))?;
+
+    let mut tree = GameTree::new();
+    let mut player = Player::Black;
+    for line in lines {
+        let mut it = line.split(',').map(|v| v.trim().parse::<i32>());
+        let (Some(Ok(x)), Some(Ok(y))) = (it.next(), it.next()) else {
+            break;
+        };
+        if x < 1 || y < 1 || x > size.0 as i32 || y > size.1 as i32 {
+            break;
+        }
+        tree.play(player, x - 1, y - 1);
+        player = if player == Player::Black {
+            Player::White
+        } else {
+            Player::Black
+        };
+    }
+    tree.select(tree.root());
+    Ok(Record {
+        name: name.to_string(),
+        size: Some(size),
+        tree,
+    })
+}
+
+/// 把一串落子写成 `.psq` 文本，用时统一记为 0。
+pub fn write(size: (u32, u32), moves: &[(i32, i32)]) -> String {
+    let mut s = format!("Piskvorky {}x{}, 11:11, 0\n", size.0, size.1);
+    for (x, y) in moves {
+        s.push_str(&format!("{},{},0\n", x + 1, y + 1));
+    }
+    s.push_str("-1\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read_round_trip() {
+        let moves = [(7, 7), (8, 6), (0, 0), (19, 14)];
+        let s = write((20, 15), &moves);
+        assert!(s.starts_with("Piskvorky 20x15,"));
+        let record = read(&s, "renju").unwrap();
+        //规则由调用者决定
+        assert_eq!(record.name, "renju");
+        assert_eq!(record.size, Some((20, 15)));
+        let tree = record.tree;
+        assert_eq!(tree.current(), tree.root());
+        assert_eq!(
+            tree.active_line(),
+            moves.iter().map(|&m| Some(m)).collect::<Vec<_>>()
+        );
+        let (_, line) = &tree.lines()[0];
+        let players = line[1..]
+            .iter()
+            .map(|&i| tree.node(i).player)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            players,
+            [Player::Black, Player::White, Player::Black, Player::White].map(Some)
+        );
+    }
+
+    #[test]
+    fn moves_end_at_the_first_other_line() {
+        let s = "Piskvorky 15x15, 11:11, 0\n8,8,120\n9,7,0\n16,1,0\n1,1,0\n";
+        let tree = read(s, "gomoku").unwrap().tree;
+        //第三手超出棋盘，之后的内容不再读取
+        assert_eq!(tree.active_line(), vec![Some((7, 7)), Some((8, 6))]);
+        assert!(read("", "gomoku").is_err());
+        assert!(read("Piskvorky, 11:11", "gomoku").is_err());
+    }
+}
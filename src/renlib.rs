@@ -0,0 +1,207 @@
+use crate::{chessboard::chessboard_trait::Player, game_tree::GameTree, sgf::Record};
+
+//节点标志位，与 RenLib 保持一致
+const DOWN: u32 = 0x80;
+const RIGHT: u32 = 0x40;
+const OLD_COMMENT: u32 = 0x20;
+const COMMENT: u32 = 0x08;
+const NO_MOVE: u32 = 0x02;
+const EXTENSION: u32 = 0x01;
+const BOARD_TEXT: u32 = 0x100;
+
+const HEADER_LEN: usize = 20;
+
+/// 解析 RenLib 的 `.lib` 棋谱库，全部变化和注释都会保留。
+///
+/// 文件由 20 字节的文件头和一串按先序排列的节点组成，每个节点两个字节：
+/// 第一个字节为坐标 `16 * y + x + 1`（0 表示没有落子），第二个字节为标志位。
+/// `DOWN` 表示下一个节点是它的子节点，`RIGHT` 表示它在子树之后还有兄弟节点。
+/// 棋盘固定为 15 * 15，黑白交替落子。文件中没有规则，棋盘类型 `name` 由调用者决定。
+pub fn read(data: &[u8], name: &str) -> Result<Record, String> {
+    if data.len() < HEADER_LEN || data[0] != 0xFF || &data[1..7] != b"RenLib" {
+        return Err("不是有效的 RenLib 文件".to_string());
+    }
+    let mut tree = GameTree::new();
+    //等待兄弟节点的父节点
+    let mut stack = vec![];
+    let mut parent = tree.root();
+    let mut i = HEADER_LEN;
+    while i + 1 < data.len() {
+        let pos = data[i] as u32;
+        let mut flags = data[i + 1] as u32;
+        i += 2;
+        if flags & EXTENSION != 0 {
+            let ext = data.get(i..i + 2).ok_or("RenLib 文件不完整")?;
+            flags |= (ext[0] as u32) << 16 | (ext[1] as u32) << 8;
+            i += 2;
+        }
+
+        tree.select(parent);
+        let node = if pos == 0 || flags & NO_MOVE != 0 {
+            parent
+        } else {
+            let x = (pos - 1) % 16;
+            let y = (pos - 1) / 16;
+            let player = if tree.depth(parent).is_multiple_of(2) {
+                Player::Black
+            } else {
+                Player::White
+            };
+            tree.play(player, x as i32, y as i32)
+        };
+
+        if flags & (COMMENT | OLD_COMMENT) != 0 {
+            let comment = read_text(data, &mut i);
+            if !comment.is_empty() {
+                tree.set_comment(comment);
+            }
+        }
+        if flags & BOARD_TEXT != 0 {
+            read_text(data, &mut i);
+        }
+
+        if flags & RIGHT != 0 {
+            stack.push(parent);
+        }
+        if flags & DOWN != 0 {
+            parent = node;
+        } else {
+            match stack.pop() {
+                Some(p) => parent = p,
+                None => break,
+            }
+        }
+    }
+    tree.select(tree.root());
+    Ok(Record {
+        name: name.to_string(),
+        size: Some((15, 15)),
+        tree,
+    })
+}
+
+//读取以 0 结尾、按两字节对齐的文本
+fn read_text(data: &[u8], i: &mut usize) -> String {
+    let start = *i;
+    let end = data[start..]
+        .iter()
+        .position(|&b| b == 0)
+        .map_or(data.len(), |p| start + p);
+    *i = end + 1;
+    if (*i - start) % 2 == 1 {
+        *i += 1;
+    }
+    String::from_utf8_lossy(&data[start..end])
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> Vec<u8> {
+        let mut data = vec![0xFF];
+        data.extend(b"RenLib");
+        data.resize(HEADER_LEN, 0);
+        data
+    }
+
+    //按 RenLib 的格式写出 `node` 的全部子树，用于测试读取
+    fn encode(tree: &GameTree, node: usize, data: &mut Vec<u8>) {
+        let children = &tree.node(node).children;
+        for (i, &child) in children.iter().enumerate() {
+            let child_node = tree.node(child);
+            let (x, y) = child_node.mv.unwrap();
+            let mut flags = 0;
+            if !child_node.children.is_empty() {
+                flags |= DOWN;
+            }
+            if i + 1 < children.len() {
+                flags |= RIGHT;
+            }
+            if !child_node.comment.is_empty() {
+                flags |= COMMENT;
+            }
+            data.push((16 * y + x + 1) as u8);
+            data.push(flags as u8);
+            if !child_node.comment.is_empty() {
+                data.extend(child_node.comment.as_bytes());
+                data.push(0);
+                if data.len() % 2 == 1 {
+                    data.push(0);
+                }
+            }
+            encode(tree, child, data);
+        }
+    }
+
+    type Line = (usize, Vec<Option<(i32, i32)>>);
+
+    //每个变化的起始深度和落子，与节点的编号无关
+    fn lines(tree: &GameTree) -> Vec<Line> {
+        tree.lines()
+            .into_iter()
+            .map(|(depth, nodes)| (depth, nodes.iter().map(|&i| tree.node(i).mv).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn move_decoding() {
+        let mut data = header();
+        //0x78 = 16 * 7 + 7 + 1 为天元 H8，0x69 = 16 * 6 + 8 + 1 为 (8, 6)
+        data.extend([0x78, DOWN as u8, 0x69, 0]);
+        let record = read(&data, "renju").unwrap();
+        assert_eq!(record.name, "renju");
+        assert_eq!(record.size, Some((15, 15)));
+        let tree = record.tree;
+        assert_eq!(tree.active_line(), vec![Some((7, 7)), Some((8, 6))]);
+        let (_, line) = &tree.lines()[0];
+        assert_eq!(tree.node(line[1]).player, Some(Player::Black));
+        assert_eq!(tree.node(line[2]).player, Some(Player::White));
+        //右下角 (14, 14)
+        let mut data = header();
+        data.extend([16 * 14 + 14 + 1, 0]);
+        let tree = read(&data, "gomoku").unwrap().tree;
+        assert_eq!(tree.active_line(), vec![Some((14, 14))]);
+    }
+
+    #[test]
+    fn variations_and_comments_round_trip() {
+        let mut tree = GameTree::new();
+        tree.play(Player::Black, 7, 7);
+        tree.set_comment("天元".to_string());
+        let first = tree.current();
+        tree.play(Player::White, 8, 6);
+        tree.play(Player::Black, 9, 5);
+        tree.set_comment("odd".to_string());
+        tree.select(first);
+        tree.play(Player::White, 8, 8);
+        tree.play(Player::Black, 6, 6);
+        tree.select(first);
+        tree.play(Player::White, 6, 8);
+        tree.select(tree.root());
+        tree.play(Player::Black, 0, 0);
+
+        let mut data = header();
+        encode(&tree, tree.root(), &mut data);
+        let read_back = read(&data, "gomoku").unwrap().tree;
+        assert_eq!(lines(&read_back), lines(&tree));
+        let comments = |tree: &GameTree| {
+            tree.lines()
+                .into_iter()
+                .flat_map(|(_, nodes)| nodes)
+                .map(|i| tree.node(i).comment.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(comments(&read_back), comments(&tree));
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        assert!(read(b"RenLib", "gomoku").is_err());
+        let mut data = header();
+        data[1] = b'X';
+        assert!(read(&data, "gomoku").is_err());
+    }
+}
@@ -0,0 +1,449 @@
+use crate::{
+    chessboard::{chessboard_trait::Player, get_default_size, get_piece, is_gomoku},
+    game_tree::{GameInfo, GameTree},
+};
+
+/// 从 SGF 读出的一局棋。
+pub struct Record {
+    /// 棋盘的唯一标识，见 `chessboard::get_all_board_names`
+    pub name: String,
+    /// 棋盘尺寸 `SZ`，未指定时为 `None`
+    pub size: Option<(u32, u32)>,
+    pub tree: GameTree,
+}
+
+//SGF 中的一个节点，属性按出现顺序保存
+struct SgfNode {
+    props: Vec<(String, Vec<String>)>,
+    children: Vec<SgfNode>,
+}
+
+impl SgfNode {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).next()
+    }
+
+    //属性的全部值，例如 AB[dd][pp]
+    fn get_all(&self, key: &str) -> impl Iterator<Item = &str> {
+        self.props
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .flat_map(|(_, v)| v.iter().map(|v| v.as_str()))
+    }
+}
+
+/// 棋盘类型与 SGF `GM` 属性的对应关系。
+/// 没有标准编号的棋盘（例如震棋）不写 `GM`，只用私有属性 `RG` 记录棋盘标识。
+fn game_number(name: &str) -> Option<u32> {
+    match name {
+        "go" | "go_japanese" | "go_tromp_taylor" => Some(1),
+        "othello" | "othello_6" | "othello_10" => Some(2),
+        "hex" | "hex_13" | "hex_19" => Some(11),
+        name if is_gomoku(name) => Some(4),
+        _ => None,
+    }
+}
+
+fn board_name(gm: &str) -> Option<&'static str> {
+    match gm {
+        "1" => Some("go"),
+        "2" => Some("othello"),
+        "11" => Some("hex"),
+        "4" => Some("gomoku"),
+        _ => None,
+    }
+}
+
+/// 把棋谱树写成 SGF 文本。
+///
+/// 坐标使用 SGF 标准的两个小写字母，第一个字母为列（从左往右），
+/// 第二个字母为行（从上往下），与 `ChessboardTrait::go` 的 `(x, y)` 一致。
+pub fn write(name: &str, size: (u32, u32), tree: &GameTree) -> String {
+    let mut s = String::from("(;FF[4]CA[UTF-8]AP[rboard:0.1.1]");
+    let gm = game_number(name);
+    if let Some(gm) = gm {
+        s.push_str(&format!("GM[{}]", gm));
+    }
+    if gm.and_then(|gm| board_name(&gm.to_string())) != Some(name) {
+        s.push_str(&format!("RG[{}]", name));
+    }
+    if size.0 == size.1 {
+        s.push_str(&format!("SZ[{}]", size.0));
+    } else {
+        s.push_str(&format!("SZ[{}:{}]", size.0, size.1));
+    }
+    let info = &tree.info;
+    for (key, value) in [
+        ("PB", &info.black),
+        ("PW", &info.white),
+        ("RE", &info.result),
+    ] {
+        if !value.is_empty() {
+            s.push_str(&format!("{}[{}]", key, escape(value)));
+        }
+    }
+    if let Some(komi) = info.komi {
+        s.push_str(&format!("KM[{}]", komi));
+    }
+    for (key, player) in [("AB", Player::Black), ("AW", Player::White)] {
+        let points = info
+            .setup
+            .iter()
+            .filter(|&&(_, _, p)| p == player)
+            .map(|&(x, y, _)| format!("[{}]", point(x, y)))
+            .collect::<String>();
+        if !points.is_empty() {
+            s.push_str(key);
+            s.push_str(&points);
+        }
+    }
+    if let Some(player) = info.to_play {
+        s.push_str(&format!("PL[{}]", player.gtp()));
+    }
+    write_props(&mut s, tree, tree.root());
+    write_children(&mut s, tree, tree.root());
+    s.push_str(")\n");
+    s
+}
+
+fn write_props(s: &mut String, tree: &GameTree, index: usize) {
+    let node = tree.node(index);
+    //认输只记录在 RE 中
+    if let Some(player) = node.player
+        && !node.resign
+    {
+        let key = if player == Player::Black { "B" } else { "W" };
+        match node.mv {
+            Some((x, y)) => s.push_str(&format!(";{}[{}]", key, point(x as u32, y as u32))),
+            //停一手写成空值
+            None => s.push_str(&format!(";{}[]", key)),
+        }
+    }
+    //终局的死子
+    if !node.dead.is_empty() {
+        s.push_str("MA");
+        for &(x, y) in &node.dead {
+            s.push_str(&format!("[{}]", point(x, y)));
+        }
+    }
+    if !node.comment.is_empty() {
+        s.push_str(&format!("C[{}]", escape(&node.comment)));
+    }
+}
+
+fn write_children(s: &mut String, tree: &GameTree, index: usize) {
+    let children = &tree.node(index).children;
+    if children.len() == 1 {
+        write_props(s, tree, children[0]);
+        write_children(s, tree, children[0]);
+    } else {
+        for &child in children {
+            s.push('(');
+            write_props(s, tree, child);
+            write_children(s, tree, child);
+            s.push(')');
+        }
+    }
+}
+
+//SGF 坐标，例如 (3, 15) -> "dp"
+fn point(x: u32, y: u32) -> String {
+    format!("{}{}", (b'a' + x as u8) as char, (b'a' + y as u8) as char)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+/// 解析 SGF 文本，只读取第一局棋。
+///
+/// 落子坐标既支持 SGF 标准的字母坐标（`B[hh]`），
+/// 也支持与引擎相同的坐标（`B[H8]`，跳过字母 I）。
+pub fn read(s: &str) -> Result<Record, String> {
+    let mut parser = Parser {
+        chars: s.chars().collect(),
+        pos: 0,
+    };
+    let root = parser.parse_game_tree()?;
+
+    let size = match root.get("SZ") {
+        Some(sz) => {
+            let mut it = sz.split(':').map(|v| v.trim().parse::<u32>());
+            match (it.next(), it.next()) {
+                (Some(Ok(x)), Some(Ok(y))) => Some((x, y)),
+                (Some(Ok(x)), None) => Some((x, x)),
+                _ => return Err(format!("无法解析棋盘大小: {}", sz)),
+            }
+        }
+        None => None,
+    };
+    let name = match root.get("RG") {
+        Some(name) => name.to_string(),
+        None => root
+            .get("GM")
+            .and_then(board_name)
+            .unwrap_or("gomoku")
+            .to_string(),
+    };
+    let board_size = size.unwrap_or_else(|| get_default_size(&name));
+
+    let mut tree = GameTree::new();
+    tree.info = GameInfo {
+        black: root.get("PB").unwrap_or_default().to_string(),
+        white: root.get("PW").unwrap_or_default().to_string(),
+        result: root.get("RE").unwrap_or_default().to_string(),
+        komi: root.get("KM").and_then(|v| v.trim().parse().ok()),
+        setup: vec![],
+        to_play: match root.get("PL") {
+            Some("B") => Some(Player::Black),
+            Some("W") => Some(Player::White),
+            _ => None,
+        },
+    };
+    for (key, player) in [("AB", Player::Black), ("AW", Player::White)] {
+        for value in root.get_all(key) {
+            let (x, y) = parse_point(value, board_size)?;
+            tree.info.setup.push((x as u32, y as u32, player));
+        }
+    }
+    add_node(&mut tree, &root, board_size)?;
+    tree.select(tree.root());
+    Ok(Record { name, size, tree })
+}
+
+//把 SGF 节点及其子节点加到当前节点之后
+fn add_node(tree: &mut GameTree, node: &SgfNode, size: (u32, u32)) -> Result<(), String> {
+    for (key, player) in [("B", Player::Black), ("W", Player::White)] {
+        //空值为停一手，FF[3] 中 tt 也表示停一手
+        if let Some(value) = node.get(key) {
+            if value.is_empty() || (value == "tt" && size.0 <= 19 && size.1 <= 19) {
+                tree.pass(player);
+            } else {
+                let (x, y) = parse_point(value, size)?;
+                tree.play(player, x, y);
+            }
+        }
+    }
+    let dead = node
+        .get_all("MA")
+        .map(|value| parse_point(value, size).map(|(x, y)| (x as u32, y as u32)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !dead.is_empty() {
+        tree.set_dead(dead);
+    }
+    if let Some(comment) = node.get("C") {
+        tree.set_comment(comment.to_string());
+    }
+    let current = tree.current();
+    for child in &node.children {
+        tree.select(current);
+        add_node(tree, child, size)?;
+    }
+    Ok(())
+}
+
+//解析落子坐标，超出 `size` 棋盘的坐标视为错误
+fn parse_point(value: &str, (size_x, size_y): (u32, u32)) -> Result<(i32, i32), String> {
+    let bytes = value.as_bytes();
+    let point = if bytes.len() == 2 && bytes.iter().all(|b| b.is_ascii_lowercase()) {
+        Some(((bytes[0] - b'a') as u32, (bytes[1] - b'a') as u32))
+    } else if bytes.first().is_some_and(|b| b.is_ascii_alphabetic()) {
+        get_piece(&value.to_uppercase(), size_x, size_y, true)
+    } else {
+        None
+    };
+    match point {
+        Some((x, y)) if x < size_x && y < size_y => Ok((x as i32, y as i32)),
+        _ => Err(format!("无法解析坐标: {}", value)),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<char> {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!(
+                "SGF 格式错误: 第 {} 个字符处缺少 '{}'",
+                self.pos, c
+            ))
+        }
+    }
+
+    //GameTree = "(" Sequence GameTree* ")"
+    //返回序列的第一个节点，后续节点和分支挂在它的 children 上
+    fn parse_game_tree(&mut self) -> Result<SgfNode, String> {
+        self.expect('(')?;
+        let mut sequence = vec![];
+        while self.peek() == Some(';') {
+            self.pos += 1;
+            sequence.push(self.parse_node()?);
+        }
+        if sequence.is_empty() {
+            return Err("SGF 格式错误: 空的节点序列".to_string());
+        }
+        let mut variations = vec![];
+        while self.peek() == Some('(') {
+            variations.push(self.parse_game_tree()?);
+        }
+        self.expect(')')?;
+
+        let mut last = sequence.pop().unwrap();
+        last.children = variations;
+        while let Some(mut node) = sequence.pop() {
+            node.children = vec![last];
+            last = node;
+        }
+        Ok(last)
+    }
+
+    fn parse_node(&mut self) -> Result<SgfNode, String> {
+        let mut props = vec![];
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+            let mut key = String::new();
+            while let Some(&c) = self.chars.get(self.pos) {
+                if !c.is_ascii_alphabetic() {
+                    break;
+                }
+                //FF[1] 中允许小写字母，只保留大写部分
+                if c.is_ascii_uppercase() {
+                    key.push(c);
+                }
+                self.pos += 1;
+            }
+            let mut values = vec![];
+            while self.peek() == Some('[') {
+                self.pos += 1;
+                values.push(self.parse_value()?);
+            }
+            if values.is_empty() {
+                return Err(format!("SGF 格式错误: 属性 {} 没有值", key));
+            }
+            props.push((key, values));
+        }
+        Ok(SgfNode {
+            props,
+            children: vec![],
+        })
+    }
+
+    fn parse_value(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        while let Some(&c) = self.chars.get(self.pos) {
+            self.pos += 1;
+            match c {
+                ']' => return Ok(value),
+                '\\' => {
+                    if let Some(&next) = self.chars.get(self.pos) {
+                        self.pos += 1;
+                        //反斜杠加换行为软换行，直接去掉
+                        if next != '\n' && next != '\r' {
+                            value.push(next);
+                        }
+                    }
+                }
+                _ => value.push(c),
+            }
+        }
+        Err("SGF 格式错误: 属性值缺少 ']'".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read_round_trip() {
+        let mut tree = GameTree::new();
+        tree.info.black = "黑方".to_string();
+        tree.info.white = "white]".to_string();
+        tree.info.komi = Some(7.5);
+        tree.info.setup = vec![(3, 3, Player::Black), (15, 15, Player::White)];
+        tree.info.to_play = Some(Player::White);
+        tree.play(Player::White, 16, 3);
+        tree.set_comment("a\\b".to_string());
+        let branch = tree.current();
+        tree.play(Player::Black, 2, 16);
+        tree.pass(Player::White);
+        tree.set_dead(vec![(2, 16), (0, 18)]);
+        tree.select(branch);
+        tree.play(Player::Black, 3, 15);
+
+        let s = write("go_japanese", (19, 19), &tree);
+        let record = read(&s).unwrap();
+        assert_eq!(record.name, "go_japanese");
+        assert_eq!(record.size, Some((19, 19)));
+        let info = &record.tree.info;
+        assert_eq!(
+            (info.black.as_str(), info.white.as_str()),
+            ("黑方", "white]")
+        );
+        assert_eq!(info.komi, Some(7.5));
+        assert_eq!(info.setup, tree.info.setup);
+        assert_eq!(info.to_play, Some(Player::White));
+
+        let mut read_tree = record.tree;
+        let (_, main_line) = &read_tree.lines()[0];
+        read_tree.select(*main_line.last().unwrap());
+        assert_eq!(read_tree.path(), vec![Some((16, 3)), Some((2, 16)), None]);
+        assert_eq!(read_tree.current_node().dead, vec![(2, 16), (0, 18)]);
+        assert_eq!(
+            read_tree.node(read_tree.node(0).children[0]).comment,
+            "a\\b"
+        );
+        assert_eq!(write("go_japanese", (19, 19), &read_tree), s);
+    }
+
+    #[test]
+    fn rectangular_board_round_trip() {
+        let mut tree = GameTree::new();
+        tree.play(Player::Black, 6, 0);
+        let s = write("connect_four", (7, 6), &tree);
+        let record = read(&s).unwrap();
+        assert_eq!(
+            (record.name.as_str(), record.size),
+            ("connect_four", Some((7, 6)))
+        );
+        assert_eq!(write("connect_four", (7, 6), &record.tree), s);
+    }
+
+    #[test]
+    fn points_outside_the_board_are_rejected() {
+        assert!(read("(;GM[4]SZ[15];B[hh];W[zz])").is_err());
+        assert!(read("(;GM[4]SZ[15];B[Q8])").is_err());
+        assert!(read("(;GM[4]SZ[15]AB[pp])").is_err());
+        assert!(read("(;GM[4]SZ[15];B[oo];W[A1])").is_ok());
+    }
+
+    #[test]
+    fn moves_rejected_by_the_board_fail_the_load() {
+        let mut state = crate::board::BoardState::default();
+        state.go(7, 7);
+        //第二个变化中白棋下在已有棋子的位置
+        let err = state.load_sgf("(;GM[4]SZ[15];B[hh](;W[hi])(;W[hh]))");
+        assert!(err.is_err());
+        assert_eq!(state.tree.path(), vec![Some((7, 7))]);
+        assert!(
+            state
+                .load_sgf("(;GM[4]SZ[15];B[hh](;W[hi])(;W[ih]))")
+                .is_ok()
+        );
+    }
+}